@@ -1,13 +1,31 @@
-use crate::config::Config;
-use crate::patroni::patroni::Patroni;
-use crate::services::actions::{Action, ActionsService};
+use crate::config::{ClusterProfile, Config};
+use crate::patroni::patroni::{ClusterInfo, NodeStatus, Patroni};
+use crate::services::actions::{Action, ActionsService, RestartOptions};
+use crate::services::alerts::AlertService;
+use crate::services::audit::{self, AuditLog, AuditRecord};
 use crate::services::cluster::ClusterService;
+use crate::services::collector::Collector;
+use crate::services::dynamic_config::{
+    ConfigEntry, config_patch, display_value, flatten_config, parse_config_value,
+};
 use crate::services::logs::LogsService;
 use crate::services::overview::OverviewService;
-use crate::ui::UI;
+use crate::services::preflight::{self, PendingChecks};
+use crate::services::rolling_restart::RollingRestart;
+use crate::services::topology;
+use crate::services::verification::VerificationPlan;
+use crate::ui::{ClusterHandle, UI};
+use anyhow::Context;
 use ratatui::Terminal;
 use ratatui::backend::Backend;
+use serde_json::Value;
 use std::cmp::PartialEq;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait for a key press before redrawing from the latest snapshot
+const TICK_RATE: Duration = Duration::from_millis(250);
 
 #[derive(Copy, Clone)]
 pub enum Tab {
@@ -15,6 +33,82 @@ pub enum Tab {
     Cluster,
     Logs,
     Actions,
+    History,
+    Config,
+    Audit,
+    /// Multi-cluster dashboard, only with `--clusters`
+    Clusters,
+}
+
+/// Text field of the Actions panel
+#[derive(Copy, Clone, PartialEq)]
+pub enum ActionField {
+    Schedule,
+    PostgresVersion,
+    RestartTimeout,
+    SyncNodeCount,
+}
+
+/// An edit of one dynamic configuration value in progress
+pub struct ConfigEdit {
+    pub path: Vec<String>,
+    pub old_value: Value,
+    /// Text typed by the user
+    pub input: String,
+    /// Showing the diff and waiting for confirmation
+    pub confirmation: bool,
+    pub confirmation_yes: bool,
+}
+
+impl ConfigEdit {
+    /// The value the input would be sent as
+    pub fn new_value(&self) -> Value {
+        parse_config_value(&self.input, &self.old_value)
+    }
+}
+
+/// Outcome per member of a reload
+pub type ReloadResults = Vec<(String, Result<(), String>)>;
+
+/// Sends an action to Patroni, filling in the outcome per member of a reload
+type SendAction = Box<dyn FnOnce(&ActionsService, &mut ReloadResults) -> anyhow::Result<()> + Send>;
+
+/// What an action sent in the background came back with
+struct ActionOutcome {
+    result: anyhow::Result<()>,
+    plan: Option<VerificationPlan>,
+    reload_results: ReloadResults,
+}
+
+/// What a request sent in the background is for
+#[derive(Clone)]
+pub enum PendingRequest {
+    Action(Action),
+    /// Edit of the dynamic configuration: dotted path and new value
+    ConfigEdit(String, Value),
+}
+
+impl PendingRequest {
+    /// Shown while waiting for Patroni
+    pub fn label(&self) -> String {
+        match self {
+            PendingRequest::Action(action) => action.as_str().to_string(),
+            PendingRequest::ConfigEdit(path, value) => format!("{path} = {}", display_value(value)),
+        }
+    }
+}
+
+/// An action being sent to Patroni in the background
+pub struct PendingAction {
+    pub request: PendingRequest,
+    outcome: Arc<Mutex<Option<ActionOutcome>>>,
+}
+
+impl PendingAction {
+    /// The outcome once Patroni answered, at most once
+    fn take_outcome(&self) -> Option<ActionOutcome> {
+        self.outcome.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
 }
 
 pub struct App {
@@ -25,12 +119,58 @@ pub struct App {
     pub log_focus_right: bool,
     pub config: Config,
 
+    // Cluster tab state
+    pub cluster_selected: usize,
+    pub cluster_detail_open: bool,
+    /// Show the replication tree instead of the member table
+    pub cluster_topology: bool,
+
     // Actions tab state
     pub action_selected: usize,
     pub action_confirmation: bool,
     pub action_confirmation_yes: bool,
     pub action_target_node: String,
     pub action_error: Option<String>,
+    /// When to run a switchover or restart (ISO 8601); empty means now
+    pub action_schedule: String,
+    // Restart conditions and options
+    pub action_restart_pending: bool,
+    pub action_restart_role: Option<&'static str>,
+    pub action_postgres_version: String,
+    pub action_restart_timeout: String,
+    pub action_reinit_force: bool,
+    /// Enable synchronous mode as strict
+    pub action_sync_strict: bool,
+    pub action_sync_node_count: String,
+    /// Text field receiving key presses, if any
+    pub action_editing: Option<ActionField>,
+    /// Whether Reload targets every member instead of the selected one
+    pub action_reload_all: bool,
+    /// Outcome per member of the last reload
+    pub action_reload_results: ReloadResults,
+    /// Action sent to Patroni and not answered yet
+    pub action_pending: Option<PendingAction>,
+    /// Pre-flight checks of the action being confirmed
+    pub action_preflight: Option<PendingChecks>,
+    /// Run the action even though a pre-flight check failed
+    pub action_override: bool,
+
+    // Config tab state
+    pub config_selected: usize,
+    /// Dotted paths of collapsed sections
+    pub config_collapsed: HashSet<String>,
+    pub config_edit: Option<ConfigEdit>,
+    /// Outcome of the last change: Ok with a summary or Err with the failure
+    pub config_result: Option<Result<String, String>>,
+
+    // Audit tab state
+    pub audit_selected: usize,
+    /// Records of the audit log, newest first, as of the last load
+    pub audit_records: Vec<AuditRecord>,
+    pub audit_error: Option<String>,
+
+    // Clusters tab state
+    pub clusters_selected: usize,
 }
 
 impl PartialEq for Tab {
@@ -40,52 +180,145 @@ impl PartialEq for Tab {
 }
 
 impl App {
-    pub fn new(config: Config) -> Self {
-        let patroni_client = Patroni::new(config.patroni_addr.clone());
-        let overview_service = OverviewService::new(patroni_client.clone(), config.clone());
-        let cluster_service = ClusterService::new(patroni_client.clone());
-        let logs_service = LogsService::new();
-
-        App {
-            current_tab: Tab::Overview,
-            ui: UI::new(overview_service, cluster_service, logs_service, config.clone()),
+    pub fn new(config: Config) -> anyhow::Result<Self> {
+        let profiles = if config.cluster_profiles.is_empty() {
+            vec![ClusterProfile {
+                name: config.cluster_name().to_string(),
+                config: config.clone(),
+            }]
+        } else {
+            config.cluster_profiles.clone()
+        };
+
+        let mut clusters = vec![];
+        for profile in profiles {
+            let patroni_client = Patroni::new(
+                profile.config.patroni_addr.clone(),
+                &profile.config.patroni_client_options()?,
+            )
+            .with_context(|| format!("Invalid settings of cluster {}", profile.name))?;
+            let cluster_service = ClusterService::new(patroni_client.clone());
+            let collector = Collector::spawn_cluster(cluster_service, &profile.config);
+
+            let alert_service = AlertService::new(&profile.config, &profile.name);
+            if alert_service.has_sinks() {
+                collector.spawn_alerts(alert_service, &profile.config);
+            }
+            let audit_log = profile
+                .config
+                .audit_log_path()
+                .map(|path| AuditLog::new(path, &profile.name));
+
+            clusters.push(ClusterHandle {
+                name: profile.name,
+                collector,
+                actions_service: ActionsService::new(patroni_client, audit_log),
+                config: profile.config,
+                rolling_restart: None,
+                verification: None,
+            });
+        }
+        // Services, HAProxy, VIP and logs belong to the local host, not to a cluster
+        clusters[0]
+            .collector
+            .spawn_host(OverviewService::new(config.clone()), LogsService::new(), &config);
+
+        let ui = UI::new(clusters, config.clone());
+        Ok(App {
+            current_tab: if ui.is_multi_cluster() { Tab::Clusters } else { Tab::Overview },
+            ui,
             log_selected: 0,
             log_scroll: 0,
             log_focus_right: false,
             config,
 
+            // Initialize Cluster tab state
+            cluster_selected: 0,
+            cluster_detail_open: false,
+            cluster_topology: false,
+
             // Initialize Actions tab state
             action_selected: 0,
             action_confirmation: false,
             action_confirmation_yes: false,
             action_target_node: String::new(),
             action_error: None,
-        }
+            action_schedule: String::new(),
+            action_restart_pending: false,
+            action_restart_role: None,
+            action_postgres_version: String::new(),
+            action_restart_timeout: String::new(),
+            action_reinit_force: false,
+            action_sync_strict: false,
+            action_sync_node_count: String::new(),
+            action_editing: None,
+            action_reload_all: true,
+            action_reload_results: vec![],
+            action_pending: None,
+            action_preflight: None,
+            action_override: false,
+
+            // Initialize Config tab state
+            config_selected: 0,
+            config_collapsed: HashSet::new(),
+            config_edit: None,
+            config_result: None,
+
+            // Initialize Audit tab state
+            audit_selected: 0,
+            audit_records: vec![],
+            audit_error: None,
+
+            // Initialize Clusters tab state
+            clusters_selected: 0,
+        })
     }
 
     pub async fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> anyhow::Result<()> {
         loop {
-            terminal.draw(|frame| self.ui.draw_ui::<B>(frame, self))?;
-
-            if crossterm::event::poll(std::time::Duration::from_millis(1000))? {
-                if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-                    use crossterm::event::{KeyCode, KeyEventKind};
-
-                    if key.kind == KeyEventKind::Press {
-                        // Handle global keys first
-                        match key.code {
-                            KeyCode::Char('q') => break,
-                            KeyCode::Char('1') => self.current_tab = Tab::Overview,
-                            KeyCode::Char('2') => self.current_tab = Tab::Cluster,
-                            KeyCode::Char('3') => self.current_tab = Tab::Logs,
-                            KeyCode::Char('4') => self.current_tab = Tab::Actions,
-                            _ => {
-                                // Handle tab-specific keys
-                                match self.current_tab {
-                                    Tab::Logs => self.handle_logs_key(key.code),
-                                    Tab::Actions => self.handle_actions_key(key.code),
-                                    _ => {} // No specific handling for other tabs yet
-                                }
+            self.finish_pending_action();
+            terminal.draw(|frame| self.ui.draw_ui(frame, self))?;
+
+            if crossterm::event::poll(TICK_RATE)?
+                && let crossterm::event::Event::Key(key) = crossterm::event::read()?
+            {
+                use crossterm::event::{KeyCode, KeyEventKind};
+
+                if key.kind == KeyEventKind::Press {
+                    // Text input takes every key, including the global ones
+                    if self.current_tab == Tab::Config && self.config_edit.is_some() {
+                        self.handle_config_edit_key(key.code);
+                        continue;
+                    }
+                    if self.current_tab == Tab::Actions && self.action_editing.is_some() {
+                        self.handle_action_field_key(key.code);
+                        continue;
+                    }
+
+                    // Handle global keys first
+                    match key.code {
+                        KeyCode::Char('q') => break,
+                        KeyCode::Char('1') => self.current_tab = Tab::Overview,
+                        KeyCode::Char('2') => self.current_tab = Tab::Cluster,
+                        KeyCode::Char('3') => self.current_tab = Tab::Logs,
+                        KeyCode::Char('4') => self.current_tab = Tab::Actions,
+                        KeyCode::Char('5') => self.current_tab = Tab::History,
+                        KeyCode::Char('6') => self.current_tab = Tab::Config,
+                        KeyCode::Char('7') => {
+                            self.current_tab = Tab::Audit;
+                            self.load_audit();
+                        }
+                        KeyCode::Char('0') if self.ui.is_multi_cluster() => self.current_tab = Tab::Clusters,
+                        _ => {
+                            // Handle tab-specific keys
+                            match self.current_tab {
+                                Tab::Cluster => self.handle_cluster_key(key.code),
+                                Tab::Logs => self.handle_logs_key(key.code),
+                                Tab::Actions => self.handle_actions_key(key.code),
+                                Tab::Config => self.handle_config_key(key.code),
+                                Tab::Audit => self.handle_audit_key(key.code),
+                                Tab::Clusters => self.handle_clusters_key(key.code),
+                                Tab::Overview | Tab::History => {} // No specific handling yet
                             }
                         }
                     }
@@ -95,6 +328,133 @@ impl App {
         Ok(())
     }
 
+    fn handle_audit_key(&mut self, key_code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        match key_code {
+            KeyCode::Down | KeyCode::Char('j') if self.audit_selected + 1 < self.audit_records.len() => {
+                self.audit_selected += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.audit_selected > 0 => {
+                self.audit_selected -= 1;
+            }
+            KeyCode::Char('r') => self.load_audit(),
+            _ => {}
+        }
+    }
+
+    /// Read the audit log of the current cluster again
+    fn load_audit(&mut self) {
+        let Some(path) = self.ui.cluster().config.audit_log_path() else {
+            self.audit_records.clear();
+            self.audit_error = Some("No audit log file is configured".to_string());
+            return;
+        };
+
+        match audit::read_records(&path) {
+            Ok(mut records) => {
+                records.reverse();
+                self.audit_records = records;
+                self.audit_error = None;
+            }
+            Err(e) => self.audit_error = Some(format!("{e:#}")),
+        }
+        self.audit_selected = self.audit_selected.min(self.audit_records.len().saturating_sub(1));
+    }
+
+    fn handle_clusters_key(&mut self, key_code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        match key_code {
+            KeyCode::Down | KeyCode::Char('j') if self.clusters_selected + 1 < self.ui.clusters.len() => {
+                self.clusters_selected += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.clusters_selected > 0 => {
+                self.clusters_selected -= 1;
+            }
+            KeyCode::Enter => {
+                self.select_cluster(self.clusters_selected);
+                self.current_tab = Tab::Overview;
+            }
+            _ => {}
+        }
+    }
+
+    /// Show `index` in the other tabs, dropping selections and pending edits
+    /// that belong to the previous cluster
+    fn select_cluster(&mut self, index: usize) {
+        if index == self.ui.current {
+            return;
+        }
+
+        self.ui.collector().watch_member(None);
+        self.ui.current = index;
+
+        self.cluster_selected = 0;
+        self.cluster_detail_open = false;
+        self.close_confirmation();
+        self.action_target_node.clear();
+        self.action_error = None;
+        self.action_editing = None;
+        self.action_reload_results.clear();
+        self.action_pending = None;
+        self.config_selected = 0;
+        self.config_collapsed.clear();
+        self.config_edit = None;
+        self.config_result = None;
+    }
+
+    fn cached_cluster_info(&self) -> Option<ClusterInfo> {
+        self.ui.collector().snapshot().cluster.map(|s| s.value)
+    }
+
+    /// Members in the order the Cluster tab shows them
+    fn cluster_members(&self) -> Vec<NodeStatus> {
+        match self.cached_cluster_info() {
+            Some(cluster_info) if self.cluster_topology => {
+                topology::build(&cluster_info).into_iter().map(|e| e.node).collect()
+            }
+            Some(cluster_info) => cluster_info.members,
+            None => vec![],
+        }
+    }
+
+    fn handle_cluster_key(&mut self, key_code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        let mut members = self.cluster_members();
+
+        match key_code {
+            KeyCode::Down | KeyCode::Char('j') if self.cluster_selected + 1 < members.len() => {
+                self.cluster_selected += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.cluster_selected > 0 => {
+                self.cluster_selected -= 1;
+            }
+            KeyCode::Enter => self.cluster_detail_open = true,
+            KeyCode::Esc => self.cluster_detail_open = false,
+            KeyCode::Char('t') => {
+                // Keep the same member selected in the other order
+                let selected = members.get(self.cluster_selected).map(|n| n.name.clone());
+                self.cluster_topology = !self.cluster_topology;
+                members = self.cluster_members();
+                self.cluster_selected = members
+                    .iter()
+                    .position(|n| Some(&n.name) == selected.as_ref())
+                    .unwrap_or(0);
+            }
+            _ => return,
+        }
+
+        // Keep the detail view following the selected member
+        let watched = if self.cluster_detail_open {
+            members.get(self.cluster_selected).cloned()
+        } else {
+            None
+        };
+        self.ui.collector().watch_member(watched);
+    }
+
     fn handle_logs_key(&mut self, key_code: crossterm::event::KeyCode) {
         use crossterm::event::KeyCode;
 
@@ -107,24 +467,20 @@ impl App {
             }
             KeyCode::Down | KeyCode::Char('j') if !self.log_focus_right => {
                 let services_len = self.config.services_list().len();
-                if self.log_selected < services_len - 1 {
+                if self.log_selected + 1 < services_len {
                     self.log_selected += 1;
                     self.log_scroll = 0;
                 }
             }
-            KeyCode::Up | KeyCode::Char('k') if !self.log_focus_right => {
-                if self.log_selected > 0 {
-                    self.log_selected -= 1;
-                    self.log_scroll = 0;
-                }
+            KeyCode::Up | KeyCode::Char('k') if !self.log_focus_right && self.log_selected > 0 => {
+                self.log_selected -= 1;
+                self.log_scroll = 0;
             }
             KeyCode::Down | KeyCode::Char('j') if self.log_focus_right => {
                 self.log_scroll += 1;
             }
-            KeyCode::Up | KeyCode::Char('k') if self.log_focus_right => {
-                if self.log_scroll > 0 {
-                    self.log_scroll -= 1;
-                }
+            KeyCode::Up | KeyCode::Char('k') if self.log_focus_right && self.log_scroll > 0 => {
+                self.log_scroll -= 1;
             }
             _ => {}
         }
@@ -134,33 +490,31 @@ impl App {
         use crossterm::event::KeyCode;
 
         match key_code {
-            KeyCode::Down | KeyCode::Char('j') => {
-                if !self.action_confirmation {
-                    let actions_len = Action::all().len();
-                    if self.action_selected < actions_len - 1 {
-                        self.action_selected += 1;
-                    }
+            KeyCode::Down | KeyCode::Char('j') if !self.action_confirmation => {
+                let actions_len = Action::all().len();
+                if self.action_selected + 1 < actions_len {
+                    self.action_selected += 1;
                 }
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if !self.action_confirmation {
-                    if self.action_selected > 0 {
-                        self.action_selected -= 1;
-                    }
-                }
+            KeyCode::Up | KeyCode::Char('k') if !self.action_confirmation && self.action_selected > 0 => {
+                self.action_selected -= 1;
             }
             KeyCode::Char('n') => self.handle_action_next_node(),
+            KeyCode::Char('o') if self.action_confirmation => self.action_override = !self.action_override,
+            KeyCode::Char(c) if !self.action_confirmation => self.handle_action_option_key(c),
             KeyCode::Enter => self.handle_action_execute(),
             KeyCode::Left | KeyCode::Right if self.action_confirmation => {
                 // Toggle between Yes and No in confirmation dialog
                 self.action_confirmation_yes = !self.action_confirmation_yes;
             }
             KeyCode::Esc => {
-                // Clear error or cancel confirmation
+                // Clear error, cancel confirmation or dismiss the finished follow-up
                 if self.action_error.is_some() {
                     self.action_error = None;
                 } else if self.action_confirmation {
-                    self.action_confirmation = false;
+                    self.close_confirmation();
+                } else if self.ui.cluster().verification.as_ref().is_some_and(|v| v.progress().finished) {
+                    self.ui.cluster_mut().verification = None;
                 }
             }
             _ => {}
@@ -173,142 +527,559 @@ impl App {
             let actions = Action::all();
             if self.action_selected < actions.len() {
                 let action = &actions[self.action_selected];
-                let cluster_info = self.ui.actions_service.get_cluster_info();
-
-                match action {
-                    Action::Switchover => {
-                        // Find next replica node
-                        let replicas: Vec<_> = cluster_info.members
-                            .iter()
-                            .filter(|n| n.role != "leader")
-                            .collect();
-
-                        if !replicas.is_empty() {
-                            if self.action_target_node.is_empty() {
-                                self.action_target_node = replicas[0].name.clone();
-                            } else {
-                                // Find current node index and select next
-                                let current_idx = replicas
-                                    .iter()
-                                    .position(|n| n.name == self.action_target_node);
-
-                                if let Some(idx) = current_idx {
-                                    let next_idx = (idx + 1) % replicas.len();
-                                    self.action_target_node = replicas[next_idx].name.clone();
-                                } else {
-                                    self.action_target_node = replicas[0].name.clone();
-                                }
-                            }
-                        }
-                    }
-                    Action::Restart | Action::Reinitialize => {
-                        // Select next node
-                        if !cluster_info.members.is_empty() {
-                            if self.action_target_node.is_empty() {
-                                self.action_target_node = cluster_info.members[0].name.clone();
-                            } else {
-                                // Find current node index and select next
-                                let current_idx = cluster_info.members
-                                    .iter()
-                                    .position(|n| n.name == self.action_target_node);
-
-                                if let Some(idx) = current_idx {
-                                    let next_idx = (idx + 1) % cluster_info.members.len();
-                                    self.action_target_node = cluster_info.members[next_idx].name.clone();
-                                } else {
-                                    self.action_target_node = cluster_info.members[0].name.clone();
-                                }
-                            }
-                        }
+                let Some(cluster_info) = self.cached_cluster_info() else {
+                    return;
+                };
+                let Some(candidates) = action.target_candidates(&cluster_info) else {
+                    return;
+                };
+
+                if !candidates.is_empty() {
+                    // Select the node after the current one, wrapping around
+                    let next_idx = candidates
+                        .iter()
+                        .position(|n| n.name == self.action_target_node)
+                        .map_or(0, |idx| (idx + 1) % candidates.len());
+                    self.action_target_node = candidates[next_idx].name.clone();
+                    if matches!(action, Action::Reload) {
+                        self.action_reload_all = false;
                     }
-                    _ => {}
                 }
             }
         }
     }
 
+    /// Set the options of the selected action
+    fn handle_action_option_key(&mut self, c: char) {
+        let actions = Action::all();
+        let Some(action) = actions.get(self.action_selected) else {
+            return;
+        };
+
+        match (action, c) {
+            (action, 't') if action.can_schedule() => self.action_editing = Some(ActionField::Schedule),
+            (Action::Restart, 'p') => self.action_restart_pending = !self.action_restart_pending,
+            (Action::Restart, 'r') => {
+                // Cycle through: any role, primary only, replicas only
+                self.action_restart_role = match self.action_restart_role {
+                    None => Some("primary"),
+                    Some("primary") => Some("replica"),
+                    Some(_) => None,
+                };
+            }
+            (Action::Restart, 'v') => self.action_editing = Some(ActionField::PostgresVersion),
+            (Action::Restart, 'o') => self.action_editing = Some(ActionField::RestartTimeout),
+            (Action::Reinitialize, 'f') => self.action_reinit_force = !self.action_reinit_force,
+            (Action::Reload, 'a') => self.action_reload_all = !self.action_reload_all,
+            (Action::EnableSyncMode, 's') => self.action_sync_strict = !self.action_sync_strict,
+            (Action::SetSyncNodeCount, 'c') => self.action_editing = Some(ActionField::SyncNodeCount),
+            (Action::RollingRestart, 'a') => {
+                if let Some(rolling_restart) = &self.ui.cluster().rolling_restart {
+                    rolling_restart.abort();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_action_field_key(&mut self, key_code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        let Some(field) = self.action_editing else {
+            return;
+        };
+        let value = match field {
+            ActionField::Schedule => &mut self.action_schedule,
+            ActionField::PostgresVersion => &mut self.action_postgres_version,
+            ActionField::RestartTimeout => &mut self.action_restart_timeout,
+            ActionField::SyncNodeCount => &mut self.action_sync_node_count,
+        };
+
+        match key_code {
+            KeyCode::Char(c) => value.push(c),
+            KeyCode::Backspace => {
+                value.pop();
+            }
+            KeyCode::Enter => self.action_editing = None,
+            // Esc clears the field, e.g. to go back to running right away
+            KeyCode::Esc => {
+                value.clear();
+                self.action_editing = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Time entered for scheduled actions; empty means right away
+    fn action_schedule(&self) -> Option<&str> {
+        let schedule = self.action_schedule.trim();
+        (!schedule.is_empty()).then_some(schedule)
+    }
+
+    /// Whether the pre-flight checks of the confirmed action, if it has
+    /// any, finished without failures or were overridden
+    fn preflight_passed(&self) -> bool {
+        match self.action_preflight.as_ref().map(PendingChecks::result) {
+            None => true,
+            Some(None) => false,
+            Some(Some(checks)) => !preflight::has_failures(&checks) || self.action_override,
+        }
+    }
+
+    fn close_confirmation(&mut self) {
+        self.action_confirmation = false;
+        self.action_preflight = None;
+        self.action_override = false;
+    }
+
+    /// Restart conditions and options as entered in the Actions panel
+    pub fn restart_options(&self) -> anyhow::Result<RestartOptions> {
+        let version = self.action_postgres_version.trim();
+        let timeout = self.action_restart_timeout.trim();
+
+        Ok(RestartOptions {
+            schedule: self.action_schedule().map(str::to_string),
+            restart_pending: self.action_restart_pending,
+            role: self.action_restart_role.map(str::to_string),
+            postgres_version: (!version.is_empty()).then(|| version.to_string()),
+            timeout: match timeout {
+                "" => None,
+                t => Some(t.parse().map_err(|_| {
+                    anyhow::anyhow!("Restart timeout must be a number of seconds, got {t:?}")
+                })?),
+            },
+        })
+    }
+
+    /// Number of synchronous standbys as entered in the Actions panel
+    pub fn sync_node_count(&self) -> anyhow::Result<u64> {
+        match self.action_sync_node_count.trim() {
+            "" => Err(anyhow::anyhow!("Enter the number of synchronous standbys first (c)")),
+            count => match count.parse() {
+                Ok(count) if count > 0 => Ok(count),
+                _ => Err(anyhow::anyhow!(
+                    "Synchronous node count must be a positive number, got {count:?}"
+                )),
+            },
+        }
+    }
+
+    /// The member the selected action is aimed at, as last seen in `/cluster`
+    fn action_target(&self) -> anyhow::Result<NodeStatus> {
+        self.cached_cluster_info()
+            .and_then(|c| c.members_map.get(&self.action_target_node).cloned())
+            .ok_or_else(|| anyhow::anyhow!("Member {} is not known", self.action_target_node))
+    }
+
     fn handle_action_execute(&mut self) {
         let actions = Action::all();
+        if self.action_pending.is_some() {
+            // One action at a time; the last one is still waiting for Patroni
+            return;
+        }
         if self.action_selected < actions.len() {
             let action = &actions[self.action_selected];
 
             if self.action_confirmation {
                 // Handle confirmation
+                if self.action_confirmation_yes && !self.preflight_passed() {
+                    // Stay in the dialog until the checks finish or are overridden
+                    return;
+                }
                 if self.action_confirmation_yes {
                     // Execute the action
-                    let result = match action {
-                        Action::Switchover => {
-                            let cluster_info = self.ui.actions_service.get_cluster_info();
-                            self.ui.actions_service.switchover(
-                                &cluster_info.leader_node_name,
-                                &self.action_target_node
-                            )
-                        }
-                        Action::Restart => {
-                            self.ui.actions_service.restart_node(&self.action_target_node)
-                        }
-                        Action::Reinitialize => {
-                            self.ui.actions_service.reinitialize_node(&self.action_target_node)
-                        }
-                        Action::PauseCluster => {
-                            self.ui.actions_service.pause_cluster()
-                        }
-                        Action::ResumeCluster => {
-                            self.ui.actions_service.resume_cluster()
-                        }
-                    };
-
-                    // Handle result
-                    if let Err(e) = result {
-                        self.action_error = Some(e.to_string());
-                    }
+                    self.execute_action(action);
 
                     // Reset confirmation state
-                    self.action_confirmation = false;
+                    self.close_confirmation();
                 } else {
                     // User selected "No"
-                    self.action_confirmation = false;
+                    self.close_confirmation();
                 }
+            } else if let Some(reason) = self.action_blocked(action) {
+                self.action_error = Some(reason);
+            } else if matches!(action, Action::Restart)
+                && let Err(e) = self.restart_options()
+            {
+                self.action_error = Some(format!("{e:#}"));
+            } else if matches!(action, Action::SetSyncNodeCount)
+                && let Err(e) = self.sync_node_count()
+            {
+                self.action_error = Some(format!("{e:#}"));
             } else if action.is_destructive() {
                 // Show confirmation dialog for destructive actions
                 self.action_confirmation = true;
                 self.action_confirmation_yes = false;
 
-                // Initialize target node if needed
-                if (matches!(action, Action::Switchover | Action::Restart | Action::Reinitialize)) 
-                    && self.action_target_node.is_empty() {
-                    let cluster_info = self.ui.actions_service.get_cluster_info();
-                    if !cluster_info.members.is_empty() {
-                        if matches!(action, Action::Switchover) {
-                            // For switchover, select first replica
-                            let replicas: Vec<_> = cluster_info.members
-                                .iter()
-                                .filter(|n| n.role != "leader")
-                                .collect();
-
-                            if !replicas.is_empty() {
-                                self.action_target_node = replicas[0].name.clone();
-                            }
-                        } else {
-                            // For other actions, select first node
-                            self.action_target_node = cluster_info.members[0].name.clone();
-                        }
-                    }
+                // Keep the target among the action's candidates, defaulting to the first
+                if let Some(cluster_info) = self.cached_cluster_info()
+                    && let Some(candidates) = action.target_candidates(&cluster_info)
+                    && !candidates.iter().any(|n| n.name == self.action_target_node)
+                    && let Some(first) = candidates.first()
+                {
+                    self.action_target_node = first.name.clone();
+                }
+
+                if action.has_preflight() {
+                    self.action_preflight = Some(match (self.cached_cluster_info(), self.action_target()) {
+                        (Some(cluster_info), Ok(target)) => PendingChecks::start(
+                            self.ui.actions_service().patroni_client.clone(),
+                            action.clone(),
+                            cluster_info,
+                            target,
+                            self.ui.cluster().config.max_replication_lag_us(),
+                        ),
+                        // Without checks the action needs the override like a failed check
+                        (None, _) => PendingChecks::not_started("Cluster state is not available yet".to_string()),
+                        (_, Err(e)) => PendingChecks::not_started(format!("{e:#}")),
+                    });
                 }
             } else {
                 // Execute non-destructive actions immediately
-                let result = match action {
-                    Action::ResumeCluster => {
-                        self.ui.actions_service.resume_cluster()
+                self.execute_action(action);
+            }
+        }
+    }
+
+    /// Send the action in the background, or report why it cannot be sent
+    fn execute_action(&mut self, action: &Action) {
+        self.ui.actions_service().clear_audit_warnings();
+        match self.action_sender(action) {
+            Ok(Some(send)) => self.send_action(action, send),
+            Ok(None) => {}
+            Err(e) => self.action_error = Some(format!("{e:#}")),
+        }
+    }
+
+    /// How to send the action to Patroni, using only the cached cluster state.
+    /// A rolling restart is started right away and runs on its own.
+    fn action_sender(&mut self, action: &Action) -> anyhow::Result<Option<SendAction>> {
+        let schedule = self.action_schedule().map(str::to_string);
+        let target = self.action_target_node.clone();
+        let send: SendAction = match action {
+            Action::Switchover => match self.cached_cluster_info() {
+                Some(ClusterInfo { leader_node_name: Some(leader), .. }) => {
+                    Box::new(move |service, _| service.switchover(&leader, &target, schedule.as_deref()))
+                }
+                Some(_) => anyhow::bail!("The cluster has no leader, use Failover"),
+                None => anyhow::bail!("Cluster state is not available yet"),
+            },
+            Action::Failover => match self.action_blocked(action) {
+                // The leader may have recovered while confirming
+                Some(reason) => anyhow::bail!(reason),
+                None => Box::new(move |service, _| service.failover(&target)),
+            },
+            Action::Restart => {
+                let node = self.action_target()?;
+                let options = self.restart_options()?;
+                Box::new(move |service, _| service.restart_node(&node, &options))
+            }
+            Action::RollingRestart => match (self.action_blocked(action), self.cached_cluster_info()) {
+                (None, Some(cluster_info)) => {
+                    let config = &self.ui.cluster().config;
+                    let rolling_restart = RollingRestart::start(
+                        self.ui.actions_service().clone(),
+                        &cluster_info,
+                        config.max_replication_lag_us(),
+                        config.patroni_interval(),
+                    );
+                    self.ui.cluster_mut().rolling_restart = Some(rolling_restart);
+                    return Ok(None);
+                }
+                (reason, _) => anyhow::bail!(reason.unwrap_or_else(|| "Cluster state is not available yet".to_string())),
+            },
+            Action::Reload => self.reload_sender()?,
+            Action::Reinitialize => {
+                let node = self.action_target()?;
+                let force = self.action_reinit_force;
+                Box::new(move |service, _| service.reinitialize_node(&node, force))
+            }
+            Action::PauseCluster => Box::new(|service, _| service.pause_cluster()),
+            Action::ResumeCluster => Box::new(|service, _| service.resume_cluster()),
+            Action::CancelScheduledSwitchover => Box::new(|service, _| service.cancel_scheduled_switchover()),
+            Action::CancelScheduledRestart => {
+                let node = self.action_target()?;
+                Box::new(move |service, _| service.cancel_scheduled_restart(&node))
+            }
+            Action::EnableSyncMode => {
+                let strict = self.action_sync_strict;
+                Box::new(move |service, _| service.set_synchronous_mode(true, Some(strict)))
+            }
+            Action::DisableSyncMode => Box::new(|service, _| service.set_synchronous_mode(false, None)),
+            Action::SetSyncNodeCount => {
+                let count = self.sync_node_count()?;
+                Box::new(move |service, _| service.set_synchronous_node_count(count))
+            }
+        };
+        Ok(Some(send))
+    }
+
+    /// Plan the follow-up and send the action without blocking the UI; the
+    /// outcome is picked up by `finish_pending_action`
+    fn send_action(&mut self, action: &Action, send: SendAction) {
+        let plan = self.plan_verification(action);
+        self.send_in_background(PendingRequest::Action(action.clone()), plan, send);
+    }
+
+    fn send_in_background(
+        &mut self,
+        request: PendingRequest,
+        plan: impl FnOnce() -> Option<VerificationPlan> + Send + 'static,
+        send: SendAction,
+    ) {
+        let actions_service = self.ui.actions_service().clone();
+        let outcome = Arc::new(Mutex::new(None));
+
+        let slot = outcome.clone();
+        tokio::task::spawn_blocking(move || {
+            // Planned before sending, the start time of a member to restart is compared afterwards
+            let plan = plan();
+            let mut reload_results = vec![];
+            let result = send(&actions_service, &mut reload_results);
+            *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(ActionOutcome { result, plan, reload_results });
+        });
+
+        self.action_pending = Some(PendingAction { request, outcome });
+    }
+
+    /// Show the outcome of the pending action once Patroni answered
+    fn finish_pending_action(&mut self) {
+        let Some((request, outcome)) = self
+            .action_pending
+            .as_ref()
+            .and_then(|pending| Some((pending.request.clone(), pending.take_outcome()?)))
+        else {
+            return;
+        };
+        self.action_pending = None;
+
+        let action = match request {
+            PendingRequest::Action(action) => action,
+            PendingRequest::ConfigEdit(path, value) => {
+                self.config_result = Some(match outcome.result {
+                    Ok(()) => {
+                        // Show the new value without waiting for the next poll
+                        let collector = self.ui.collector().clone();
+                        tokio::task::spawn_blocking(move || collector.refresh_dynamic_config());
+                        Ok(format!("Set {path} to {value}"))
                     }
-                    _ => Ok(()) // Should not happen
-                };
+                    Err(e) => Err(format!("Failed to update {path}: {e:#}")),
+                });
+                return;
+            }
+        };
+
+        if matches!(action, Action::Reload) {
+            self.action_reload_results = outcome.reload_results;
+        }
+        match outcome.result {
+            Ok(()) => self.start_verification(outcome.plan),
+            Err(e) => self.action_error = Some(format!("{e:#}")),
+        }
+
+        // Show the new sync settings without waiting for the next poll
+        if matches!(action, Action::EnableSyncMode | Action::DisableSyncMode | Action::SetSyncNodeCount) {
+            let collector = self.ui.collector().clone();
+            tokio::task::spawn_blocking(move || collector.refresh_dynamic_config());
+        }
+    }
+
+    /// What the cluster should show after the selected action, from the
+    /// cached cluster state. Planning may ask Patroni, so it runs with the action.
+    fn plan_verification(&self, action: &Action) -> impl FnOnce() -> Option<VerificationPlan> + Send + 'static {
+        let patroni_client = self.ui.actions_service().patroni_client.clone();
+        let action = action.clone();
+        let cluster_info = self.cached_cluster_info();
+        let target = self.action_target().ok();
+        let scheduled = self.action_schedule().is_some();
+        let sync_node_count = self.sync_node_count().ok();
+
+        move || {
+            let cluster_info = cluster_info?;
+            if matches!(action, Action::SetSyncNodeCount) {
+                return Some(VerificationPlan::sync_node_count(&cluster_info, sync_node_count?));
+            }
+            VerificationPlan::new(
+                &ClusterService::new(patroni_client),
+                &action,
+                &cluster_info,
+                target.as_ref(),
+                scheduled,
+            )
+        }
+    }
+
+    /// Follow the cluster after an action Patroni accepted
+    fn start_verification(&mut self, plan: Option<VerificationPlan>) {
+        if let Some(plan) = plan {
+            let config = &self.ui.cluster().config;
+            let verification = plan.start(
+                self.ui.actions_service().patroni_client.clone(),
+                config.max_replication_lag_us(),
+                config.patroni_interval(),
+            );
+            self.ui.cluster_mut().verification = Some(verification);
+        }
+    }
+
+    /// Reload the selected member or all of them at once, keeping the outcome per member
+    fn reload_sender(&self) -> anyhow::Result<SendAction> {
+        let nodes = if self.action_reload_all {
+            self.cached_cluster_info()
+                .map(|c| c.members)
+                .ok_or_else(|| anyhow::anyhow!("Cluster state is not available yet"))?
+        } else {
+            vec![self.action_target()?]
+        };
+
+        Ok(Box::new(move |service, results| {
+            *results = std::thread::scope(|scope| {
+                let reloads: Vec<_> = nodes
+                    .iter()
+                    .map(|node| scope.spawn(move || service.reload_node(node).map_err(|e| format!("{e:#}"))))
+                    .collect();
+                nodes
+                    .iter()
+                    .zip(reloads)
+                    .map(|(node, reload)| {
+                        let result = reload.join().unwrap_or_else(|_| Err("Reload stopped on an internal error".to_string()));
+                        (node.name.clone(), result)
+                    })
+                    .collect()
+            });
+            Ok(())
+        }))
+    }
+
+    /// Why an action cannot be run in the current cluster state, if it cannot
+    fn action_blocked(&self, action: &Action) -> Option<String> {
+        if matches!(action, Action::RollingRestart)
+            && self.ui.cluster().rolling_restart.as_ref().is_some_and(RollingRestart::is_running)
+        {
+            return Some("A rolling restart is already running".to_string());
+        }
+
+        match self.cached_cluster_info() {
+            Some(cluster_info) => {
+                action.blocked_reason(&cluster_info, self.ui.cluster().config.max_replication_lag_us())
+            }
+            None => action
+                .needs_cluster_state()
+                .then(|| "Cluster state is not available yet".to_string()),
+        }
+    }
+
+    /// Rows of the dynamic configuration tree as currently shown
+    fn config_entries(&self) -> Vec<ConfigEntry> {
+        self.ui
+            .collector()
+            .snapshot()
+            .dynamic_config
+            .map_or(vec![], |s| flatten_config(&s.value, &self.config_collapsed))
+    }
+
+    fn handle_config_key(&mut self, key_code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        let entries = self.config_entries();
+        // Sections may have been collapsed or the document may have shrunk
+        self.config_selected = self.config_selected.min(entries.len().saturating_sub(1));
+        let Some(entry) = entries.get(self.config_selected) else {
+            return;
+        };
 
-                // Handle result
-                if let Err(e) = result {
-                    self.action_error = Some(e.to_string());
+        match key_code {
+            KeyCode::Down | KeyCode::Char('j') if self.config_selected + 1 < entries.len() => {
+                self.config_selected += 1;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.config_selected > 0 => {
+                self.config_selected -= 1;
+            }
+            KeyCode::Left | KeyCode::Char('h') if entry.value.is_none() => {
+                self.config_collapsed.insert(entry.dotted_path());
+            }
+            KeyCode::Right | KeyCode::Char('l') if entry.value.is_none() => {
+                self.config_collapsed.remove(&entry.dotted_path());
+            }
+            KeyCode::Enter => match &entry.value {
+                // Toggle sections, edit leaf values
+                None => {
+                    let path = entry.dotted_path();
+                    if !self.config_collapsed.remove(&path) {
+                        self.config_collapsed.insert(path);
+                    }
                 }
+                Some(value) => {
+                    self.config_result = None;
+                    self.config_edit = Some(ConfigEdit {
+                        path: entry.path.clone(),
+                        old_value: value.clone(),
+                        input: display_value(value),
+                        confirmation: false,
+                        confirmation_yes: false,
+                    });
+                }
+            },
+            KeyCode::Esc => self.config_result = None,
+            _ => {}
+        }
+    }
+
+    fn handle_config_edit_key(&mut self, key_code: crossterm::event::KeyCode) {
+        use crossterm::event::KeyCode;
+
+        let Some(edit) = self.config_edit.as_mut() else {
+            return;
+        };
+
+        if edit.confirmation {
+            match key_code {
+                KeyCode::Left | KeyCode::Right => edit.confirmation_yes = !edit.confirmation_yes,
+                // Back to editing the value
+                KeyCode::Esc => edit.confirmation = false,
+                KeyCode::Enter if edit.confirmation_yes => self.handle_config_apply(),
+                KeyCode::Enter => self.config_edit = None,
+                _ => {}
+            }
+            return;
+        }
+
+        match key_code {
+            KeyCode::Char(c) => edit.input.push(c),
+            KeyCode::Backspace => {
+                edit.input.pop();
             }
+            KeyCode::Enter if edit.new_value() == edit.old_value => {
+                self.config_edit = None;
+                self.config_result = Some(Ok("Value unchanged, nothing sent".to_string()));
+            }
+            KeyCode::Enter => {
+                edit.confirmation = true;
+                edit.confirmation_yes = false;
+            }
+            KeyCode::Esc => self.config_edit = None,
+            _ => {}
         }
     }
+
+    /// Send the confirmed edit as `PATCH /config` in the background
+    fn handle_config_apply(&mut self) {
+        if self.action_pending.is_some() {
+            // One request at a time; the confirmation stays open to send it later
+            return;
+        }
+        let Some(edit) = self.config_edit.take() else {
+            return;
+        };
+        let new_value = edit.new_value();
+        let patch = config_patch(&edit.path, new_value.clone());
+
+        self.config_result = None;
+        self.ui.actions_service().clear_audit_warnings();
+        self.send_in_background(
+            PendingRequest::ConfigEdit(edit.path.join("."), new_value),
+            || None,
+            Box::new(move |service, _| service.patch_config(&patch)),
+        );
+    }
 }
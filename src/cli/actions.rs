@@ -0,0 +1,806 @@
+use crate::cli::{EXIT_DECLINED, EXIT_FAILED, EXIT_OK, EXIT_UNAVAILABLE, EXIT_USAGE, patroni_error};
+use crate::config::Config;
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, NodeStatus, Patroni};
+use crate::services::actions::{Action, ActionsService, RestartOptions};
+use crate::services::audit::AuditLog;
+use crate::services::cluster::ClusterService;
+use crate::services::preflight::{self, CheckStatus};
+use crate::services::rolling_restart::{RollingProgress, RollingRestart, StepStatus};
+use crate::services::verification::{VerificationPlan, VerificationProgress};
+use clap::{Args, Subcommand};
+use serde_json::{Value, json};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Subcommands running one Actions tab operation
+#[derive(Subcommand, Debug, Clone)]
+pub enum ActionCommand {
+    /// Change the leader to a replica
+    Switchover {
+        /// Replica that becomes the new leader
+        #[arg(long)]
+        candidate: String,
+        /// Switch over at this time (ISO 8601 with time zone) instead of now
+        #[arg(long)]
+        scheduled_at: Option<String>,
+        /// Switch over even if a pre-flight check fails
+        #[arg(long)]
+        override_checks: bool,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Promote a replica when there is no healthy leader
+    Failover {
+        /// Replica to promote
+        #[arg(long)]
+        candidate: String,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Restart PostgreSQL on a member
+    Restart {
+        /// Member to restart
+        node: String,
+        /// Only restart if the member has a restart pending
+        #[arg(long)]
+        pending: bool,
+        /// Only restart if the member has this role
+        #[arg(long, value_parser = ["primary", "replica"])]
+        role: Option<String>,
+        /// Only restart if the running PostgreSQL is older than this version
+        #[arg(long)]
+        postgres_version: Option<String>,
+        /// Seconds to wait for PostgreSQL to come up before failing
+        #[arg(long)]
+        timeout: Option<u64>,
+        /// Restart at this time (ISO 8601 with time zone) instead of now
+        #[arg(long)]
+        scheduled_at: Option<String>,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Restart every member in turn and wait until it completes
+    RollingRestart {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Reload the configuration of a member, or of every member
+    Reload {
+        /// Member to reload; all members if omitted
+        node: Option<String>,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Rebuild the data directory of a member from the leader
+    Reinit {
+        /// Member to reinitialize
+        node: String,
+        /// Reinitialize even while PostgreSQL is running
+        #[arg(long)]
+        force: bool,
+        /// Reinitialize even if a pre-flight check fails
+        #[arg(long)]
+        override_checks: bool,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Pause automatic failover in the cluster
+    Pause {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Resume automatic failover in the cluster
+    Resume {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Cancel the pending scheduled switchover
+    CancelSwitchover {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Cancel the pending scheduled restart of a member
+    CancelRestart {
+        /// Member whose restart is cancelled
+        node: String,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Make commits wait until synchronous standbys have received them
+    EnableSyncMode {
+        /// Let commits wait even when no synchronous standby is available
+        #[arg(long)]
+        strict: bool,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Stop waiting for synchronous standbys
+    DisableSyncMode {
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+    /// Set how many synchronous standbys the leader waits for
+    SyncNodeCount {
+        /// Number of synchronous standbys
+        #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+        count: u64,
+        #[command(flatten)]
+        args: ActionArgs,
+    },
+}
+
+/// Options shared by all action subcommands
+#[derive(Args, Debug, Clone)]
+pub struct ActionArgs {
+    /// Do not ask for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Print the result as JSON on stdout
+    #[arg(long)]
+    pub json: bool,
+
+    /// Wait until the cluster shows the outcome of the action, failing if it
+    /// is not reached in time
+    #[arg(long)]
+    pub wait: bool,
+}
+
+impl ActionCommand {
+    /// Subcommand name, as reported in JSON output
+    fn name(&self) -> &'static str {
+        match self {
+            ActionCommand::Switchover { .. } => "switchover",
+            ActionCommand::Failover { .. } => "failover",
+            ActionCommand::Restart { .. } => "restart",
+            ActionCommand::RollingRestart { .. } => "rolling-restart",
+            ActionCommand::Reload { .. } => "reload",
+            ActionCommand::Reinit { .. } => "reinit",
+            ActionCommand::Pause { .. } => "pause",
+            ActionCommand::Resume { .. } => "resume",
+            ActionCommand::CancelSwitchover { .. } => "cancel-switchover",
+            ActionCommand::CancelRestart { .. } => "cancel-restart",
+            ActionCommand::EnableSyncMode { .. } => "enable-sync-mode",
+            ActionCommand::DisableSyncMode { .. } => "disable-sync-mode",
+            ActionCommand::SyncNodeCount { .. } => "sync-node-count",
+        }
+    }
+
+    fn action(&self) -> Action {
+        match self {
+            ActionCommand::Switchover { .. } => Action::Switchover,
+            ActionCommand::Failover { .. } => Action::Failover,
+            ActionCommand::Restart { .. } => Action::Restart,
+            ActionCommand::RollingRestart { .. } => Action::RollingRestart,
+            ActionCommand::Reload { .. } => Action::Reload,
+            ActionCommand::Reinit { .. } => Action::Reinitialize,
+            ActionCommand::Pause { .. } => Action::PauseCluster,
+            ActionCommand::Resume { .. } => Action::ResumeCluster,
+            ActionCommand::CancelSwitchover { .. } => Action::CancelScheduledSwitchover,
+            ActionCommand::CancelRestart { .. } => Action::CancelScheduledRestart,
+            ActionCommand::EnableSyncMode { .. } => Action::EnableSyncMode,
+            ActionCommand::DisableSyncMode { .. } => Action::DisableSyncMode,
+            ActionCommand::SyncNodeCount { .. } => Action::SetSyncNodeCount,
+        }
+    }
+
+    fn args(&self) -> &ActionArgs {
+        match self {
+            ActionCommand::Switchover { args, .. }
+            | ActionCommand::Failover { args, .. }
+            | ActionCommand::Restart { args, .. }
+            | ActionCommand::RollingRestart { args }
+            | ActionCommand::Reload { args, .. }
+            | ActionCommand::Reinit { args, .. }
+            | ActionCommand::Pause { args }
+            | ActionCommand::Resume { args }
+            | ActionCommand::CancelSwitchover { args }
+            | ActionCommand::CancelRestart { args, .. }
+            | ActionCommand::EnableSyncMode { args, .. }
+            | ActionCommand::DisableSyncMode { args }
+            | ActionCommand::SyncNodeCount { args, .. } => args,
+        }
+    }
+
+    /// Member named on the command line, if the subcommand takes one
+    fn target(&self) -> Option<&str> {
+        match self {
+            ActionCommand::Switchover { candidate, .. } | ActionCommand::Failover { candidate, .. } => Some(candidate),
+            ActionCommand::Restart { node, .. } | ActionCommand::Reinit { node, .. } | ActionCommand::CancelRestart { node, .. } => {
+                Some(node)
+            }
+            ActionCommand::Reload { node, .. } => node.as_deref(),
+            ActionCommand::RollingRestart { .. }
+            | ActionCommand::Pause { .. }
+            | ActionCommand::Resume { .. }
+            | ActionCommand::CancelSwitchover { .. }
+            | ActionCommand::EnableSyncMode { .. }
+            | ActionCommand::DisableSyncMode { .. }
+            | ActionCommand::SyncNodeCount { .. } => None,
+        }
+    }
+
+    /// Value the subcommand sets, shown in the confirmation question
+    fn setting(&self) -> Option<String> {
+        match self {
+            ActionCommand::EnableSyncMode { strict: true, .. } => Some("(strict)".to_string()),
+            ActionCommand::SyncNodeCount { count, .. } => Some(format!("to {count}")),
+            _ => None,
+        }
+    }
+
+    /// Whether failed pre-flight checks are overridden
+    fn override_checks(&self) -> bool {
+        match self {
+            ActionCommand::Switchover { override_checks, .. } | ActionCommand::Reinit { override_checks, .. } => {
+                *override_checks
+            }
+            _ => false,
+        }
+    }
+
+    fn scheduled_at(&self) -> Option<&str> {
+        match self {
+            ActionCommand::Switchover { scheduled_at, .. } | ActionCommand::Restart { scheduled_at, .. } => {
+                scheduled_at.as_deref()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Result of a subcommand that reached Patroni
+struct Outcome {
+    ok: bool,
+    message: String,
+    /// Per-member lines printed before the message
+    lines: Vec<String>,
+    /// Extra fields of the JSON output
+    details: Option<(&'static str, Value)>,
+}
+
+impl Outcome {
+    fn done(message: String) -> Self {
+        Outcome {
+            ok: true,
+            message,
+            lines: vec![],
+            details: None,
+        }
+    }
+}
+
+/// Why a subcommand stopped before completing
+enum Failure {
+    Usage(String),
+    Declined(String),
+    /// The cluster state does not allow the action
+    Blocked(String),
+    Error(anyhow::Error),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Usage(_) => EXIT_USAGE,
+            Failure::Declined(_) => EXIT_DECLINED,
+            Failure::Blocked(_) => EXIT_FAILED,
+            Failure::Error(err) => match patroni_error(err) {
+                Some(PatroniError::Http { .. }) | None => EXIT_FAILED,
+                Some(_) => EXIT_UNAVAILABLE,
+            },
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Failure::Usage(_) => "usage",
+            Failure::Declined(_) => "declined",
+            Failure::Blocked(_) => "blocked",
+            Failure::Error(err) => patroni_error(err).map_or("error", PatroniError::kind),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Failure::Usage(message) | Failure::Declined(message) | Failure::Blocked(message) => message.clone(),
+            Failure::Error(err) => format!("{err:#}"),
+        }
+    }
+}
+
+impl From<anyhow::Error> for Failure {
+    fn from(err: anyhow::Error) -> Self {
+        Failure::Error(err)
+    }
+}
+
+/// Run `command` against the cluster, print the result and return the exit code
+pub(super) async fn run(config: &Config, command: &ActionCommand) -> i32 {
+    let json_output = command.args().json;
+    let result = execute(config, command).await;
+
+    let code = match &result {
+        Ok(outcome) if outcome.ok => EXIT_OK,
+        Ok(_) => EXIT_FAILED,
+        Err(failure) => failure.exit_code(),
+    };
+
+    match (&result, json_output) {
+        (Ok(outcome), true) => {
+            let mut output = json!({
+                "command": command.name(),
+                "ok": outcome.ok,
+                "message": outcome.message,
+            });
+            if let Some((key, value)) = &outcome.details {
+                output[*key] = value.clone();
+            }
+            println!("{output}");
+        }
+        (Ok(outcome), false) => {
+            for line in &outcome.lines {
+                println!("{line}");
+            }
+            if outcome.ok {
+                println!("{}", outcome.message);
+            } else {
+                eprintln!("{}", outcome.message);
+            }
+        }
+        (Err(failure), true) => {
+            let output = json!({
+                "command": command.name(),
+                "ok": false,
+                "error": failure.message(),
+                "error_kind": failure.kind(),
+            });
+            println!("{output}");
+        }
+        (Err(failure), false) => eprintln!("Error: {}", failure.message()),
+    }
+
+    code
+}
+
+async fn execute(config: &Config, command: &ActionCommand) -> Result<Outcome, Failure> {
+    let (prepare_config, prepare_command) = (config.clone(), command.clone());
+    let Prepared {
+        patroni_client,
+        actions_service,
+        cluster,
+        target,
+        plan,
+    } = blocking(move || prepare(&prepare_config, &prepare_command)).await?;
+
+    let outcome = match command {
+        ActionCommand::RollingRestart { args } => Ok(rolling_restart(config, actions_service.clone(), &cluster, args.json).await),
+        _ => {
+            let (command, actions_service) = (command.clone(), actions_service.clone());
+            blocking(move || send(&command, target, &actions_service, &cluster)).await
+        }
+    };
+
+    for warning in actions_service.audit_warnings() {
+        eprintln!("Warning: audit log: {warning}");
+    }
+
+    match (plan, outcome) {
+        (Some(plan), Ok(sent)) if sent.ok => Ok(verify(config, plan, patroni_client, sent, command.args().json).await),
+        (_, outcome) => outcome,
+    }
+}
+
+/// What is known once the action is allowed and confirmed
+struct Prepared {
+    patroni_client: Patroni,
+    actions_service: ActionsService,
+    cluster: ClusterInfo,
+    target: Option<NodeStatus>,
+    plan: Option<VerificationPlan>,
+}
+
+/// Fetch the cluster state, check that the action is possible there and
+/// confirm it
+fn prepare(config: &Config, command: &ActionCommand) -> Result<Prepared, Failure> {
+    let patroni_client = config
+        .patroni_client_options()
+        .and_then(|options| Patroni::new(config.patroni_addr.clone(), &options))
+        .map_err(|e| Failure::Usage(format!("{e:#}")))?;
+    let audit_log = config
+        .audit_log_path()
+        .map(|path| AuditLog::new(path, config.cluster_name()));
+    let actions_service = ActionsService::new(patroni_client.clone(), audit_log);
+    let cluster = ClusterService::new(patroni_client.clone())
+        .get_cluster_info()
+        .map_err(|e| Failure::Error(anyhow::Error::new(e).context("Failed to fetch cluster state")))?;
+
+    let action = command.action();
+    if let Some(reason) = action.blocked_reason(&cluster, config.max_replication_lag_us()) {
+        return Err(Failure::Blocked(reason));
+    }
+    let target = match command.target() {
+        Some(name) => Some(find_target(&action, &cluster, name)?),
+        None => None,
+    };
+    if action.has_preflight()
+        && let Some(node) = &target
+    {
+        preflight(config, command, &patroni_client, &cluster, node)?;
+    }
+    if action.is_destructive() && !command.args().yes {
+        confirm(&action, &cluster, target.as_ref(), command.setting(), command.scheduled_at())?;
+    }
+
+    // Planned before sending, the start time of a member to restart is compared afterwards
+    let plan = if let ActionCommand::SyncNodeCount { count, args } = command
+        && args.wait
+    {
+        Some(VerificationPlan::sync_node_count(&cluster, *count))
+    } else if command.args().wait {
+        VerificationPlan::new(
+            &ClusterService::new(patroni_client.clone()),
+            &action,
+            &cluster,
+            target.as_ref(),
+            command.scheduled_at().is_some(),
+        )
+    } else {
+        None
+    };
+
+    Ok(Prepared {
+        patroni_client,
+        actions_service,
+        cluster,
+        target,
+        plan,
+    })
+}
+
+/// Run `f`, which waits on Patroni or the terminal, off the async workers
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T, Failure> + Send + 'static) -> Result<T, Failure> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Failure::Error(anyhow::Error::new(e).context("Request task failed")))?
+}
+
+/// Send the request of `command`, aimed at `target` if it names a member
+fn send(
+    command: &ActionCommand,
+    target: Option<NodeStatus>,
+    actions_service: &ActionsService,
+    cluster: &ClusterInfo,
+) -> Result<Outcome, Failure> {
+    let when = command.scheduled_at().map_or_else(String::new, |at| format!(" at {at}"));
+    match (command, target) {
+        (ActionCommand::Switchover { candidate, scheduled_at, .. }, _) => {
+            let leader = cluster
+                .leader_node_name
+                .as_deref()
+                .ok_or_else(|| Failure::Blocked("The cluster has no leader, use Failover".to_string()))?;
+            actions_service.switchover(leader, candidate, scheduled_at.as_deref())?;
+            Ok(Outcome::done(match scheduled_at {
+                Some(_) => format!("Switchover from {leader} to {candidate} scheduled{when}"),
+                None => format!("Switched over from {leader} to {candidate}"),
+            }))
+        }
+        (ActionCommand::Failover { candidate, .. }, _) => {
+            actions_service.failover(candidate)?;
+            Ok(Outcome::done(format!("Failed over to {candidate}")))
+        }
+        (
+            ActionCommand::Restart {
+                pending,
+                role,
+                postgres_version,
+                timeout,
+                scheduled_at,
+                ..
+            },
+            Some(node),
+        ) => {
+            let options = RestartOptions {
+                schedule: scheduled_at.clone(),
+                restart_pending: *pending,
+                role: role.clone(),
+                postgres_version: postgres_version.clone(),
+                timeout: *timeout,
+            };
+            actions_service.restart_node(&node, &options)?;
+            Ok(Outcome::done(match scheduled_at {
+                Some(_) => format!("Restart of {} scheduled{when}", node.name),
+                None => format!("Restarted {}", node.name),
+            }))
+        }
+        (ActionCommand::Reload { .. }, target) => {
+            let nodes = match target {
+                Some(node) => vec![node],
+                None => cluster.members.clone(),
+            };
+            Ok(reload(actions_service, &nodes))
+        }
+        (ActionCommand::Reinit { force, .. }, Some(node)) => {
+            actions_service.reinitialize_node(&node, *force)?;
+            Ok(Outcome::done(format!("Reinitializing {}", node.name)))
+        }
+        (ActionCommand::Pause { .. }, _) => {
+            actions_service.pause_cluster()?;
+            Ok(Outcome::done(format!("Paused cluster {}", cluster.scope)))
+        }
+        (ActionCommand::Resume { .. }, _) => {
+            actions_service.resume_cluster()?;
+            Ok(Outcome::done(format!("Resumed cluster {}", cluster.scope)))
+        }
+        (ActionCommand::CancelSwitchover { .. }, _) => {
+            actions_service.cancel_scheduled_switchover()?;
+            Ok(Outcome::done("Cancelled the scheduled switchover".to_string()))
+        }
+        (ActionCommand::CancelRestart { .. }, Some(node)) => {
+            actions_service.cancel_scheduled_restart(&node)?;
+            Ok(Outcome::done(format!("Cancelled the scheduled restart of {}", node.name)))
+        }
+        (ActionCommand::EnableSyncMode { strict, .. }, _) => {
+            actions_service.set_synchronous_mode(true, Some(*strict))?;
+            Ok(Outcome::done(format!(
+                "Enabled {}synchronous mode on {}",
+                if *strict { "strict " } else { "" },
+                cluster.scope
+            )))
+        }
+        (ActionCommand::DisableSyncMode { .. }, _) => {
+            actions_service.set_synchronous_mode(false, None)?;
+            Ok(Outcome::done(format!("Disabled synchronous mode on {}", cluster.scope)))
+        }
+        (ActionCommand::SyncNodeCount { count, .. }, _) => {
+            actions_service.set_synchronous_node_count(*count)?;
+            Ok(Outcome::done(format!("Set synchronous_node_count to {count} on {}", cluster.scope)))
+        }
+        // Followed step by step in `rolling_restart`, not a single request
+        (ActionCommand::RollingRestart { .. }, _) => Err(Failure::Usage(
+            "rolling-restart is not a single request".to_string(),
+        )),
+        (ActionCommand::Restart { .. } | ActionCommand::Reinit { .. } | ActionCommand::CancelRestart { .. }, None) => {
+            Err(Failure::Usage(format!("{} needs a member", command.name())))
+        }
+    }
+}
+
+/// Look up the member named on the command line among those the action can be aimed at
+fn find_target(action: &Action, cluster: &ClusterInfo, name: &str) -> Result<NodeStatus, Failure> {
+    let candidates = action.target_candidates(cluster).unwrap_or_default();
+    if let Some(node) = candidates.iter().find(|n| n.name == name) {
+        return Ok((*node).clone());
+    }
+
+    if !cluster.members_map.contains_key(name) {
+        return Err(Failure::Usage(format!("Member {name} is not in cluster {}", cluster.scope)));
+    }
+    let names: Vec<&str> = candidates.iter().map(|n| n.name.as_str()).collect();
+    Err(Failure::Usage(if names.is_empty() {
+        format!("{} is not possible for any member", action.as_str())
+    } else {
+        format!(
+            "{} is not possible for {name}, choose one of: {}",
+            action.as_str(),
+            names.join(", ")
+        )
+    }))
+}
+
+/// Run the pre-flight checks of the action, listing them on stderr, and
+/// refuse to go on if one fails unless overridden
+fn preflight(
+    config: &Config,
+    command: &ActionCommand,
+    patroni_client: &Patroni,
+    cluster: &ClusterInfo,
+    target: &NodeStatus,
+) -> Result<(), Failure> {
+    let checks = preflight::run_checks(
+        &ClusterService::new(patroni_client.clone()),
+        &command.action(),
+        cluster,
+        target,
+        config.max_replication_lag_us(),
+    );
+    if !command.args().json {
+        for check in &checks {
+            eprintln!("[{}] {}: {}", check.status.as_str(), check.name, check.detail);
+        }
+    }
+
+    if !preflight::has_failures(&checks) || command.override_checks() {
+        return Ok(());
+    }
+    let failed: Vec<String> = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .map(|c| format!("{}: {}", c.name, c.detail))
+        .collect();
+    Err(Failure::Blocked(format!(
+        "Pre-flight checks failed ({}); pass --override-checks to run anyway",
+        failed.join("; ")
+    )))
+}
+
+/// Ask on the terminal before a destructive action. Without a terminal the
+/// action is refused so scripts have to pass `--yes` explicitly.
+fn confirm(
+    action: &Action,
+    cluster: &ClusterInfo,
+    target: Option<&NodeStatus>,
+    setting: Option<String>,
+    scheduled_at: Option<&str>,
+) -> Result<(), Failure> {
+    let mut question = match target {
+        Some(node) => format!("{} {}", action.as_str(), node.name),
+        None => action.as_str().to_string(),
+    };
+    if let Some(setting) = setting {
+        question.push_str(&format!(" {setting}"));
+    }
+    if let Some(at) = scheduled_at {
+        question.push_str(&format!(" at {at}"));
+    }
+    question.push_str(&format!(" (cluster {})", cluster.scope));
+
+    if !std::io::stdin().is_terminal() {
+        return Err(Failure::Declined(format!(
+            "Not confirmed: {question}; pass --yes to run without a terminal"
+        )));
+    }
+
+    eprint!("{question}? [y/N] ");
+    std::io::stderr().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|e| Failure::Declined(format!("Failed to read confirmation: {e}")))?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(Failure::Declined("Aborted".to_string())),
+    }
+}
+
+fn reload(actions_service: &ActionsService, nodes: &[NodeStatus]) -> Outcome {
+    let results: Vec<(String, Result<(), String>)> = nodes
+        .iter()
+        .map(|node| {
+            let result = actions_service.reload_node(node).map_err(|e| format!("{e:#}"));
+            (node.name.clone(), result)
+        })
+        .collect();
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    Outcome {
+        ok: failed == 0,
+        message: match failed {
+            0 => format!("Reloaded {} member(s)", results.len()),
+            n => format!("Reload failed on {n} of {} member(s)", results.len()),
+        },
+        lines: results
+            .iter()
+            .map(|(name, result)| match result {
+                Ok(()) => format!("{name}: reloaded"),
+                Err(e) => format!("{name}: {e}"),
+            })
+            .collect(),
+        details: Some((
+            "members",
+            results
+                .iter()
+                .map(|(name, result)| match result {
+                    Ok(()) => json!({"name": name, "ok": true}),
+                    Err(e) => json!({"name": name, "ok": false, "error": e}),
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Follow the cluster until it shows the outcome of the action, reporting
+/// step changes on stderr
+async fn verify(config: &Config, plan: VerificationPlan, patroni_client: Patroni, sent: Outcome, quiet: bool) -> Outcome {
+    let verification = plan.start(patroni_client, config.max_replication_lag_us(), config.patroni_interval());
+    let progress = follow(config, quiet, || verification.progress()).await;
+
+    Outcome {
+        ok: progress.succeeded,
+        message: format!("{}. {}", sent.message, progress.message),
+        lines: sent.lines,
+        details: Some((
+            "verification",
+            json!({
+                "succeeded": progress.succeeded,
+                "message": progress.message,
+                "steps": steps_json(&progress),
+            }),
+        )),
+    }
+}
+
+/// Run a rolling restart to the end, reporting step changes on stderr
+async fn rolling_restart(
+    config: &Config,
+    actions_service: ActionsService,
+    cluster: &ClusterInfo,
+    quiet: bool,
+) -> Outcome {
+    let rolling_restart = RollingRestart::start(
+        actions_service,
+        cluster,
+        config.max_replication_lag_us(),
+        config.patroni_interval(),
+    );
+
+    let progress = follow(config, quiet, || rolling_restart.progress()).await;
+
+    Outcome {
+        ok: progress.steps.iter().all(|s| s.status == StepStatus::Done),
+        message: progress.message.clone(),
+        lines: vec![],
+        details: Some(("steps", steps_json(&progress))),
+    }
+}
+
+/// Progress of a background run made of steps
+trait StepProgress {
+    /// Status and description of every step, in order
+    fn steps(&self) -> Vec<(StepStatus, &str)>;
+    fn finished(&self) -> bool;
+}
+
+impl StepProgress for VerificationProgress {
+    fn steps(&self) -> Vec<(StepStatus, &str)> {
+        self.steps.iter().map(|s| (s.status, s.description.as_str())).collect()
+    }
+
+    fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl StepProgress for RollingProgress {
+    fn steps(&self) -> Vec<(StepStatus, &str)> {
+        self.steps.iter().map(|s| (s.status, s.description.as_str())).collect()
+    }
+
+    fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Poll `progress` until the run finishes, printing each step on stderr
+/// when its status changes unless `quiet`
+async fn follow<P: StepProgress>(config: &Config, quiet: bool, progress: impl Fn() -> P) -> P {
+    let mut reported: Vec<StepStatus> = vec![];
+    loop {
+        let current = progress();
+        let steps = current.steps();
+        if !quiet {
+            for (i, (status, description)) in steps.iter().enumerate() {
+                if reported.get(i) != Some(status) && *status != StepStatus::Pending {
+                    eprintln!("[{}] {description}", status.as_str());
+                }
+            }
+        }
+        reported = steps.iter().map(|(status, _)| *status).collect();
+
+        if current.finished() {
+            return current;
+        }
+        tokio::time::sleep(config.patroni_interval()).await;
+    }
+}
+
+/// Steps of a finished run for the JSON output
+fn steps_json(progress: &impl StepProgress) -> Value {
+    progress
+        .steps()
+        .iter()
+        .map(|(status, description)| json!({"description": description, "status": status.as_str()}))
+        .collect()
+}
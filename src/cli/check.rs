@@ -0,0 +1,248 @@
+use crate::config::Config;
+use crate::patroni::patroni::{ClusterInfo, Patroni};
+use crate::services::cluster::ClusterService;
+use crate::services::overview::OverviewService;
+use crate::system;
+
+/// Nagios plugin states. Variants are ordered by how much attention they
+/// need, so the overall state is the maximum of the individual ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum State {
+    Ok,
+    Warning,
+    Unknown,
+    Critical,
+}
+
+impl State {
+    fn label(&self) -> &'static str {
+        match self {
+            State::Ok => "OK",
+            State::Warning => "WARNING",
+            State::Unknown => "UNKNOWN",
+            State::Critical => "CRITICAL",
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        match self {
+            State::Ok => 0,
+            State::Warning => 1,
+            State::Critical => 2,
+            State::Unknown => 3,
+        }
+    }
+}
+
+/// Findings of all checks: problems with their state, and perfdata
+#[derive(Default)]
+struct Report {
+    problems: Vec<(State, String)>,
+    perfdata: Vec<String>,
+    /// Short description of what was checked, shown when all is well
+    summary: Vec<String>,
+}
+
+impl Report {
+    fn problem(&mut self, state: State, message: String) {
+        self.problems.push((state, message));
+    }
+
+    fn state(&self) -> State {
+        self.problems.iter().map(|(state, _)| *state).max().unwrap_or(State::Ok)
+    }
+}
+
+/// Evaluate the cluster and the local services, print one Nagios plugin
+/// line and return the plugin exit code
+pub(super) fn run(config: &Config, vip: Option<&str>) -> i32 {
+    let mut report = Report::default();
+
+    let cluster = config
+        .patroni_client_options()
+        .and_then(|options| Patroni::new(config.patroni_addr.clone(), &options))
+        .and_then(|client| Ok(ClusterService::new(client).get_cluster_info()?));
+    check_cluster(&mut report, cluster, config.max_replication_lag_us());
+    check_services(&mut report, config);
+    if let Some(vip) = vip {
+        check_vip(&mut report, vip);
+    }
+
+    let state = report.state();
+    let text = if report.problems.is_empty() {
+        report.summary.join(", ")
+    } else {
+        let mut problems = report.problems.clone();
+        // Most severe first
+        problems.sort_by_key(|(state, _)| std::cmp::Reverse(*state));
+        problems.into_iter().map(|(_, message)| message).collect::<Vec<_>>().join("; ")
+    };
+    println!("CLUP {} - {} | {}", state.label(), text, report.perfdata.join(" "));
+
+    state.exit_code()
+}
+
+/// A missing leader or a replica that is down is critical, lag and pending
+/// restarts are warnings, and a cluster that cannot be fetched is unknown
+fn check_cluster(report: &mut Report, cluster: anyhow::Result<ClusterInfo>, max_lag_us: u64) {
+    let cluster = match cluster {
+        Ok(cluster) => cluster,
+        Err(e) => {
+            report.problem(State::Unknown, format!("Patroni: {e:#}"));
+            return;
+        }
+    };
+    if !cluster.has_healthy_leader() {
+        let state = cluster.leader().map_or("missing", |n| n.state.as_str());
+        report.problem(State::Critical, format!("leader {state}"));
+    }
+
+    let replicas: Vec<_> = cluster.members.iter().filter(|n| n.role != "leader").collect();
+    let mut streaming = 0;
+    // Replicas are only itemized when the overall health check fails
+    let replication_ok = cluster.replication_ok(max_lag_us);
+    for node in &replicas {
+        let state_ok = matches!(node.state.as_str(), "running" | "streaming");
+        if state_ok {
+            streaming += 1;
+        }
+        if replication_ok {
+            continue;
+        }
+
+        match node.lag {
+            _ if !state_ok => report.problem(State::Critical, format!("{} is {}", node.name, node.state)),
+            None => report.problem(State::Warning, format!("{} lag unknown", node.name)),
+            Some(lag) if lag > max_lag_us => {
+                report.problem(State::Warning, format!("{} lag {} above limit", node.name, lag))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for node in cluster.members.iter().filter(|n| n.pending_restart) {
+        report.problem(State::Warning, format!("{} pending restart", node.name));
+    }
+
+    let max_lag = replicas.iter().filter_map(|n| n.lag).max().unwrap_or(0);
+    report.summary.push(format!(
+        "cluster {} leader {}, {}/{} replicas streaming",
+        cluster.scope,
+        cluster.leader_node_name.as_deref().unwrap_or("none"),
+        streaming,
+        replicas.len()
+    ));
+    report.perfdata.push(format!("members={}", cluster.members.len()));
+    report.perfdata.push(format!("replicas_streaming={};;;0;{}", streaming, replicas.len()));
+    report.perfdata.push(format!("max_lag={max_lag};{max_lag_us};;0"));
+}
+
+fn check_services(report: &mut Report, config: &Config) {
+    let services = config.services_list();
+    let mut active = 0;
+    for service in &services {
+        if system::is_service_active(service) {
+            active += 1;
+        } else {
+            report.problem(State::Critical, format!("{service} inactive"));
+        }
+    }
+    report.summary.push(format!("{}/{} services active", active, services.len()));
+    report.perfdata.push(format!("services_active={};;;0;{}", active, services.len()));
+
+    if services.iter().any(|s| s == "haproxy") {
+        let (up, total) = OverviewService::new(config.clone()).fetch_haproxy_backend_stats();
+        match (up, total) {
+            (_, 0) => report.problem(State::Warning, format!("no HAProxy backends on {}", config.haproxy_socket)),
+            (0, _) => report.problem(State::Critical, format!("HAProxy backends 0/{total} up")),
+            (up, total) if up < total => report.problem(State::Warning, format!("HAProxy backends {up}/{total} up")),
+            _ => report.summary.push(format!("HAProxy backends {up}/{total} up")),
+        }
+        report.perfdata.push(format!("haproxy_backends_up={up};;;0;{total}"));
+    }
+}
+
+/// The VIP must be held by exactly one host: this one, or one answering ARP
+fn check_vip(report: &mut Report, vip: &str) {
+    let here = usize::from(system::has_local_address(vip));
+    let holders = match system::count_arp_holders(vip) {
+        Ok(others) => here + others,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            report.problem(State::Unknown, format!("VIP {vip}: arping is not installed"));
+            return;
+        }
+        Err(e) => {
+            report.problem(State::Unknown, format!("VIP {vip}: arping failed: {e}"));
+            return;
+        }
+    };
+
+    match holders {
+        0 => report.problem(State::Critical, format!("VIP {vip} not present")),
+        1 => report.summary.push(format!(
+            "VIP {vip} {}",
+            if here == 1 { "held here" } else { "held by another node" }
+        )),
+        n => report.problem(State::Critical, format!("VIP {vip} held by {n} nodes")),
+    }
+    report.perfdata.push(format!("vip_holders={holders};;1;0"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patroni::testing::{cluster, member};
+    use serde_json::json;
+
+    const MAX_LAG_US: u64 = 1_000;
+
+    /// Overall state and problem messages for `cluster`
+    fn check(cluster: anyhow::Result<ClusterInfo>) -> (State, Vec<String>) {
+        let mut report = Report::default();
+        check_cluster(&mut report, cluster, MAX_LAG_US);
+        (report.state(), report.problems.into_iter().map(|(_, message)| message).collect())
+    }
+
+    #[test]
+    fn cluster_states_map_to_nagios_states() {
+        let streaming = || json!({"lag": 0});
+        let cases = [
+            ("healthy", json!({}), streaming(), State::Ok, vec![]),
+            ("lagging replica", json!({}), json!({"lag": 5_000}), State::Warning, vec!["pg2 lag 5000 above limit"]),
+            ("pending restart", json!({"pending_restart": true}), streaming(), State::Warning, vec!["pg1 pending restart"]),
+            ("replica down", json!({}), json!({"state": "stopped", "lag": 0}), State::Critical, vec!["pg2 is stopped"]),
+            ("leader down", json!({"state": "stopped"}), streaming(), State::Critical, vec!["leader stopped"]),
+        ];
+        for (name, leader, replica, state, problems) in cases {
+            let members = vec![
+                member("pg1", "leader", leader),
+                member("pg2", "replica", replica),
+                member("pg3", "replica", streaming()),
+            ];
+
+            assert_eq!(check(Ok(cluster(members))), (state, problems.iter().map(|p| p.to_string()).collect()), "{name}");
+        }
+    }
+
+    #[test]
+    fn missing_leader_is_critical() {
+        let members = vec![member("pg2", "replica", json!({"lag": 0})), member("pg3", "replica", json!({"lag": 0}))];
+
+        assert_eq!(check(Ok(cluster(members))), (State::Critical, vec!["leader missing".to_string()]));
+    }
+
+    #[test]
+    fn fetch_error_is_unknown() {
+        let (state, problems) = check(Err(anyhow::anyhow!("connection refused")));
+
+        assert_eq!(state, State::Unknown);
+        assert_eq!(problems, ["Patroni: connection refused"]);
+    }
+
+    #[test]
+    fn exit_codes_follow_nagios() {
+        let codes: Vec<i32> = [State::Ok, State::Warning, State::Critical, State::Unknown].iter().map(State::exit_code).collect();
+
+        assert_eq!(codes, [0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::cli::EXIT_USAGE;
+use crate::config::Config;
+use crate::patroni::patroni::Patroni;
+use crate::services::alerts::AlertService;
+use crate::services::cluster::ClusterService;
+use crate::services::collector::{Collector, Snapshot};
+use crate::services::overview::OverviewService;
+use std::fmt::{Display, Write};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Longest request head accepted from a scraper
+const MAX_REQUEST_HEAD: usize = 8192;
+/// Connections that do not send a request in time are dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run the collectors without the TUI and serve their latest snapshot on
+/// `/metrics`. Only returns if the listener cannot be set up.
+pub(super) async fn run(config: &Config, listen: &str, vip: Option<&str>) -> i32 {
+    let patroni_client = match config
+        .patroni_client_options()
+        .and_then(|options| Patroni::new(config.patroni_addr.clone(), &options))
+    {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            return EXIT_USAGE;
+        }
+    };
+
+    // `:9187` listens on all interfaces
+    let addr = match listen.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{port}"),
+        None => listen.to_string(),
+    };
+    let listener = match TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: cannot listen on {addr}: {e}");
+            return EXIT_USAGE;
+        }
+    };
+
+    let collector = Collector::spawn_metrics(
+        OverviewService::new(config.clone()),
+        ClusterService::new(patroni_client),
+        config,
+    );
+    let alert_service = AlertService::new(config, config.cluster_name());
+    let alerting = alert_service.has_sinks();
+    if alerting {
+        collector.spawn_alerts(alert_service, config);
+    }
+    let max_lag_us = config.max_replication_lag_us();
+    eprintln!("Serving metrics on http://{addr}/metrics");
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let collector = collector.clone();
+                let vip = vip.map(str::to_string);
+                tokio::spawn(async move {
+                    // Scrapers retry on their own, failed connections are not reported
+                    let served = serve(stream, &collector, max_lag_us, alerting, vip.as_deref());
+                    let _ = tokio::time::timeout(REQUEST_TIMEOUT, served).await;
+                });
+            }
+            Err(e) => eprintln!("Error: failed to accept connection: {e}"),
+        }
+    }
+}
+
+/// Answer a single HTTP request on `stream`
+async fn serve(
+    mut stream: TcpStream,
+    collector: &Collector,
+    max_lag_us: u64,
+    alerting: bool,
+    vip: Option<&str>,
+) -> std::io::Result<()> {
+    let mut buf = vec![0; MAX_REQUEST_HEAD];
+    let mut len = 0;
+    // The head is all we need; a GET has no body
+    while !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        if len == buf.len() {
+            return respond(&mut stream, "431 Request Header Fields Too Large", "").await;
+        }
+        let read = stream.read(&mut buf[len..]).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        len += read;
+    }
+
+    let head = String::from_utf8_lossy(&buf[..len]);
+    let mut request_line = head.split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("").split('?').next().unwrap_or("");
+
+    match (method, path) {
+        ("GET", "/metrics") => respond(&mut stream, "200 OK", &render(&collector.snapshot(), max_lag_us, alerting, vip)).await,
+        ("GET", "/") => respond(&mut stream, "200 OK", "clup exporter, metrics are on /metrics\n").await,
+        ("GET", _) => respond(&mut stream, "404 Not Found", "").await,
+        _ => respond(&mut stream, "405 Method Not Allowed", "").await,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Metrics in the Prometheus text exposition format
+#[derive(Default)]
+struct Metrics {
+    out: String,
+}
+
+impl Metrics {
+    /// Start a gauge family; its samples must follow before the next family
+    fn gauge(&mut self, name: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP clup_{name} {help}");
+        let _ = writeln!(self.out, "# TYPE clup_{name} gauge");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(key, value)| format!("{key}=\"{}\"", escape_label(value)))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(self.out, "clup_{name} {value}");
+        } else {
+            let _ = writeln!(self.out, "clup_{name}{{{}}} {value}", labels.join(","));
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Render the snapshot. Cluster metrics are left out while Patroni cannot be
+/// reached rather than repeating the last known state.
+fn render(snapshot: &Snapshot, max_lag_us: u64, alerting: bool, vip: Option<&str>) -> String {
+    let mut m = Metrics::default();
+    let cluster = snapshot
+        .cluster
+        .as_ref()
+        .filter(|_| snapshot.cluster_error.is_none())
+        .map(|s| &s.value);
+
+    m.gauge("patroni_up", "Whether the last Patroni cluster fetch succeeded");
+    m.sample("patroni_up", &[], u8::from(cluster.is_some()));
+    if let Some(sample) = &snapshot.cluster {
+        m.gauge("cluster_age_seconds", "Seconds since the cluster state was last fetched successfully");
+        m.sample("cluster_age_seconds", &[], sample.age().as_secs_f64());
+    }
+
+    if let Some(c) = cluster {
+        let scope = c.scope.as_str();
+        m.gauge("cluster_replication_ok", "Whether all replicas stream with lag under the limit");
+        m.sample("cluster_replication_ok", &[("scope", scope)], u8::from(c.replication_ok(max_lag_us)));
+
+        m.gauge("member_info", "Cluster member with its role and state as labels");
+        for n in &c.members {
+            let labels = [
+                ("scope", scope),
+                ("member", n.name.as_str()),
+                ("role", n.role.as_str()),
+                ("state", n.state.as_str()),
+                ("host", n.host.as_str()),
+            ];
+            m.sample("member_info", &labels, 1);
+        }
+
+        m.gauge("member_leader", "Whether the member holds the leader lock");
+        for n in &c.members {
+            let leader = c.is_leader(&n.name);
+            m.sample("member_leader", &[("scope", scope), ("member", &n.name)], u8::from(leader));
+        }
+
+        m.gauge("member_running", "Whether the member is running or streaming");
+        for n in &c.members {
+            let running = matches!(n.state.as_str(), "running" | "streaming");
+            m.sample("member_running", &[("scope", scope), ("member", &n.name)], u8::from(running));
+        }
+
+        m.gauge("member_lag", "Replication lag as reported by Patroni; absent when unknown");
+        for n in c.members.iter().filter(|n| n.role != "leader") {
+            if let Some(lag) = n.lag {
+                m.sample("member_lag", &[("scope", scope), ("member", &n.name)], lag);
+            }
+        }
+
+        m.gauge("member_timeline", "PostgreSQL timeline of the member");
+        for n in &c.members {
+            if let Some(timeline) = n.timeline {
+                m.sample("member_timeline", &[("scope", scope), ("member", &n.name)], timeline);
+            }
+        }
+
+        m.gauge("member_pending_restart", "Whether the member needs a restart to apply settings");
+        for n in &c.members {
+            let pending = u8::from(n.pending_restart);
+            m.sample("member_pending_restart", &[("scope", scope), ("member", &n.name)], pending);
+        }
+    }
+
+    if let Some(sample) = &snapshot.overview {
+        let o = &sample.value;
+
+        m.gauge("service_up", "Whether the systemd service is active");
+        for c in &o.components {
+            m.sample("service_up", &[("service", &c.name)], u8::from(c.up));
+        }
+
+        m.gauge("service_uptime_seconds", "Seconds since the systemd service became active");
+        for c in &o.components {
+            if let Some(uptime) = c.uptime_secs {
+                m.sample("service_uptime_seconds", &[("service", &c.name)], uptime);
+            }
+        }
+
+        m.gauge("service_errors", "Error and fatal lines among the last 300 journal lines");
+        for c in &o.components {
+            m.sample("service_errors", &[("service", &c.name)], c.errors);
+        }
+
+        let (up, total) = o.haproxy_backends;
+        m.gauge("haproxy_backends_up", "HAProxy backends in state UP");
+        m.sample("haproxy_backends_up", &[], up);
+        m.gauge("haproxy_backends", "HAProxy backends found on the stats socket");
+        m.sample("haproxy_backends", &[], total);
+
+        // The label stays the same when the VIP moves, so the series does too
+        m.gauge("vip_owned", "Whether this host holds the Keepalived VIP");
+        match vip {
+            Some(vip) => m.sample("vip_owned", &[("vip", vip)], u8::from(o.vip == vip)),
+            None => m.sample("vip_owned", &[], u8::from(o.vip != "-")),
+        }
+    }
+
+    if alerting {
+        m.gauge("alerts_failing", "Whether delivering the most recent alerts to a sink failed");
+        m.sample("alerts_failing", &[], u8::from(snapshot.alert_error.is_some()));
+        if let Some(alert) = &snapshot.last_alert {
+            m.gauge("last_alert_age_seconds", "Seconds since the most recent alert was raised");
+            m.sample("last_alert_age_seconds", &[], alert.age().as_secs_f64());
+        }
+    }
+
+    m.out
+}
@@ -0,0 +1,65 @@
+use crate::config::Config;
+use crate::patroni::error::PatroniError;
+use clap::Subcommand;
+
+mod actions;
+mod check;
+mod metrics;
+mod status;
+
+pub use actions::ActionCommand;
+pub use status::OutputFormat;
+
+/// The command completed
+pub const EXIT_OK: i32 = 0;
+/// Patroni rejected the action, or the cluster state does not allow it
+pub const EXIT_FAILED: i32 = 1;
+/// Invalid arguments, e.g. a member that is not in the cluster
+pub const EXIT_USAGE: i32 = 2;
+/// Patroni could not be reached or answered with something unexpected
+pub const EXIT_UNAVAILABLE: i32 = 3;
+/// The confirmation was declined or could not be asked
+pub const EXIT_DECLINED: i32 = 4;
+
+/// Run a single command without the TUI and exit
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print the overview of the local host and the cluster once
+    Status {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Evaluate the cluster health as a Nagios/Icinga plugin (exit 0/1/2/3)
+    Check {
+        /// Keepalived VIP that must be held by exactly one host (uses arping)
+        #[arg(long)]
+        vip: Option<String>,
+    },
+    /// Run the collectors headlessly and serve Prometheus metrics
+    ServeMetrics {
+        /// Address to listen on; `:PORT` listens on all interfaces
+        #[arg(long, default_value = ":9187")]
+        listen: String,
+        /// Keepalived VIP whose ownership `vip_owned` reports; without it,
+        /// whether this host holds any VIP
+        #[arg(long)]
+        vip: Option<String>,
+    },
+    #[command(flatten)]
+    Action(ActionCommand),
+}
+
+/// Run `command`, print its result and return the process exit code
+pub async fn run(config: &Config, command: &Command) -> i32 {
+    match command {
+        Command::Status { format } => status::run(config, *format),
+        Command::Check { vip } => check::run(config, vip.as_deref()),
+        Command::ServeMetrics { listen, vip } => metrics::run(config, listen, vip.as_deref()).await,
+        Command::Action(action) => actions::run(config, action).await,
+    }
+}
+
+/// The Patroni error behind a failed operation, if it came from the REST API
+fn patroni_error(err: &anyhow::Error) -> Option<&PatroniError> {
+    err.chain().find_map(|e| e.downcast_ref::<PatroniError>())
+}
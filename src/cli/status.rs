@@ -0,0 +1,374 @@
+use crate::cli::{EXIT_OK, EXIT_UNAVAILABLE};
+use crate::config::Config;
+use crate::patroni::patroni::Patroni;
+use crate::services::cluster::ClusterService;
+use crate::services::overview::{ComponentStatus, OverviewService};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned plain text, like the Overview tab
+    Table,
+    Json,
+    Yaml,
+}
+
+/// Everything the Overview tab shows, gathered once
+#[derive(Debug, Serialize)]
+struct StatusReport {
+    hostname: String,
+    ip: String,
+    vip: String,
+    cluster: Option<ClusterReport>,
+    /// Why the cluster state could not be fetched
+    cluster_error: Option<String>,
+    replication_ok: bool,
+    haproxy: HaproxyReport,
+    components: Vec<ComponentStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct ClusterReport {
+    scope: String,
+    /// Member answering on the configured Patroni address
+    node: String,
+    /// `null` while no member holds the leader lock
+    leader: Option<String>,
+    api_endpoint: String,
+    members: Vec<MemberReport>,
+}
+
+#[derive(Debug, Serialize)]
+struct MemberReport {
+    name: String,
+    role: String,
+    state: String,
+    host: String,
+    port: Option<u16>,
+    timeline: Option<u64>,
+    lag: Option<u64>,
+    pending_restart: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct HaproxyReport {
+    backends_up: u32,
+    backends_total: u32,
+}
+
+/// Gather the overview, print it in `format` and return the exit code.
+/// The report is printed even when Patroni is unreachable.
+pub(super) fn run(config: &Config, format: OutputFormat) -> i32 {
+    let report = gather(config);
+    let code = if report.cluster.is_some() { EXIT_OK } else { EXIT_UNAVAILABLE };
+
+    match format {
+        OutputFormat::Table => print!("{}", to_table(&report)),
+        OutputFormat::Json => match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{json}"),
+            Err(e) => eprintln!("Error: {e}"),
+        },
+        OutputFormat::Yaml => match serde_json::to_value(&report) {
+            Ok(value) => print!("{}", to_yaml(&value, 0)),
+            Err(e) => eprintln!("Error: {e}"),
+        },
+    }
+
+    code
+}
+
+fn gather(config: &Config) -> StatusReport {
+    let overview = OverviewService::new(config.clone()).get_overview();
+    let cluster = config
+        .patroni_client_options()
+        .and_then(|options| Patroni::new(config.patroni_addr.clone(), &options))
+        .and_then(|client| Ok(ClusterService::new(client).get_cluster_info()?));
+
+    let (cluster, cluster_error) = match cluster {
+        Ok(cluster) => (Some(cluster), None),
+        Err(e) => (None, Some(format!("{e:#}"))),
+    };
+
+    StatusReport {
+        hostname: overview.hostname,
+        ip: overview.ip,
+        vip: overview.vip,
+        replication_ok: cluster
+            .as_ref()
+            .is_some_and(|c| c.replication_ok(config.max_replication_lag_us())),
+        cluster: cluster.map(|c| ClusterReport {
+            members: c
+                .members
+                .iter()
+                .map(|n| MemberReport {
+                    name: n.name.clone(),
+                    role: n.role.clone(),
+                    state: n.state.clone(),
+                    host: n.host.clone(),
+                    port: n.port,
+                    timeline: n.timeline,
+                    lag: n.lag,
+                    pending_restart: n.pending_restart,
+                })
+                .collect(),
+            scope: c.scope,
+            node: c.node_name,
+            leader: c.leader_node_name,
+            api_endpoint: c.api_endpoint,
+        }),
+        cluster_error,
+        haproxy: HaproxyReport {
+            backends_up: overview.haproxy_backends.0,
+            backends_total: overview.haproxy_backends.1,
+        },
+        components: overview.components,
+    }
+}
+
+fn to_table(report: &StatusReport) -> String {
+    let mut out = String::new();
+
+    match &report.cluster {
+        Some(c) => out.push_str(&format!(
+            "Cluster: {}   Node: {}   Leader: {}   API: {}\n",
+            c.scope,
+            c.node,
+            c.leader.as_deref().unwrap_or("-"),
+            c.api_endpoint
+        )),
+        None => out.push_str("Cluster: -\n"),
+    }
+    out.push_str(&format!(
+        "Replication: {}   HAProxy: {}/{} backends up   VIP: {}   Host: {} ({})\n",
+        if report.replication_ok { "OK" } else { "FAILED" },
+        report.haproxy.backends_up,
+        report.haproxy.backends_total,
+        report.vip,
+        report.hostname,
+        report.ip,
+    ));
+    if let Some(error) = &report.cluster_error {
+        out.push_str(&format!("Patroni: {error}\n"));
+    }
+
+    if let Some(c) = &report.cluster {
+        out.push('\n');
+        let rows = c
+            .members
+            .iter()
+            .map(|m| {
+                vec![
+                    m.name.clone(),
+                    m.role.clone(),
+                    m.state.clone(),
+                    m.host.clone(),
+                    optional(m.port),
+                    optional(m.timeline),
+                    optional(m.lag),
+                    if m.pending_restart { "*" } else { "" }.to_string(),
+                ]
+            })
+            .collect();
+        push_table(
+            &mut out,
+            &["NAME", "ROLE", "STATE", "HOST", "PORT", "TL", "LAG", "RESTART"],
+            rows,
+        );
+    }
+
+    out.push('\n');
+    let rows = report
+        .components
+        .iter()
+        .map(|c| {
+            vec![
+                c.name.clone(),
+                if c.up { "UP" } else { "DOWN" }.to_string(),
+                c.errors.to_string(),
+                c.uptime.clone(),
+                c.version.clone(),
+            ]
+        })
+        .collect();
+    push_table(&mut out, &["SERVICE", "STATUS", "ERRORS", "UPTIME", "VERSION"], rows);
+
+    out
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+/// Append `rows` under `headers` with every column padded to its widest cell
+fn push_table(out: &mut String, headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = headers.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(header).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+}
+
+/// Render a JSON value as block-style YAML, indented by `indent` spaces
+fn to_yaml(value: &Value, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut out = String::new();
+
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, item) in map {
+                if is_block(item) {
+                    out.push_str(&format!("{pad}{}:\n{}", yaml_scalar(key), to_yaml(item, indent + 2)));
+                } else {
+                    out.push_str(&format!("{pad}{}: {}\n", yaml_scalar(key), yaml_value(item)));
+                }
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                if is_block(item) {
+                    // The first line of the nested block goes right after the dash
+                    let nested = to_yaml(item, indent + 2);
+                    out.push_str(&format!("{pad}- {}", &nested[indent + 2..]));
+                } else {
+                    out.push_str(&format!("{pad}- {}\n", yaml_value(item)));
+                }
+            }
+        }
+        other => out.push_str(&format!("{pad}{}\n", yaml_value(other))),
+    }
+
+    out
+}
+
+/// Non-empty objects and arrays are written as nested blocks
+fn is_block(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+fn yaml_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => yaml_scalar(text),
+        Value::Object(_) => "{}".to_string(),
+        Value::Array(_) => "[]".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Plain string if YAML reads it back as the same string, otherwise double
+/// quoted (JSON string syntax is valid YAML)
+fn yaml_scalar(text: &str) -> String {
+    let reserved = matches!(
+        text.to_lowercase().as_str(),
+        "" | "~" | "null" | "true" | "false" | "yes" | "no" | "on" | "off" | "y" | "n"
+    );
+    let plain = !reserved
+        && !looks_numeric(text)
+        && !text.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !text.ends_with(|c: char| c.is_whitespace() || c == ':')
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.contains(|c: char| c.is_control());
+
+    if plain {
+        text.to_string()
+    } else {
+        Value::String(text.to_string()).to_string()
+    }
+}
+
+/// Whether YAML 1.1 or 1.2 reads the plain string as a number: digits with
+/// `_` separators, hexadecimal, octal, infinity and not-a-number
+fn looks_numeric(text: &str) -> bool {
+    let unsigned = text.trim_start_matches(['+', '-']);
+    let radix = unsigned
+        .strip_prefix("0x")
+        .map(|digits| (digits, 16))
+        .or_else(|| unsigned.strip_prefix("0o").map(|digits| (digits, 8)));
+
+    text.replace('_', "").parse::<f64>().is_ok()
+        || radix.is_some_and(|(digits, radix)| u64::from_str_radix(digits, radix).is_ok())
+        || matches!(unsigned.to_lowercase().as_str(), ".inf" | ".nan")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn quotes_keywords() {
+        for text in ["true", "False", "yes", "No", "on", "OFF", "y", "n", "null", "~"] {
+            assert_eq!(yaml_scalar(text), format!("\"{text}\""));
+        }
+    }
+
+    #[test]
+    fn quotes_numbers() {
+        for text in ["42", "-1", "3.14", "1e3", "1_000", "0x1f", "0o17", ".inf", "-.Inf", ".nan"] {
+            assert_eq!(yaml_scalar(text), format!("\"{text}\""));
+        }
+        assert_eq!(yaml_scalar("pg1"), "pg1");
+        assert_eq!(yaml_scalar("17.2"), "\"17.2\"");
+    }
+
+    #[test]
+    fn quotes_colons_and_comments() {
+        assert_eq!(yaml_scalar("10.0.0.1:8008"), "10.0.0.1:8008");
+        assert_eq!(yaml_scalar("lag: high"), "\"lag: high\"");
+        assert_eq!(yaml_scalar("leader:"), "\"leader:\"");
+        assert_eq!(yaml_scalar(":leader"), "\":leader\"");
+        assert_eq!(yaml_scalar("# note"), "\"# note\"");
+        assert_eq!(yaml_scalar("up #1"), "\"up #1\"");
+        assert_eq!(yaml_scalar("pg#1"), "pg#1");
+    }
+
+    #[test]
+    fn quotes_empty_strings() {
+        assert_eq!(yaml_scalar(""), "\"\"");
+        assert_eq!(yaml_scalar(" padded"), "\" padded\"");
+        assert_eq!(to_yaml(&json!({"leader": ""}), 0), "leader: \"\"\n");
+    }
+
+    #[test]
+    fn nests_arrays() {
+        let value = json!({
+            "members": [{"name": "pg1", "tags": ["a", "b"]}, {"name": "pg2", "tags": []}],
+            "matrix": [[1, 2], [3]],
+        });
+
+        assert_eq!(
+            to_yaml(&value, 0),
+            "\
+matrix:
+  - - 1
+    - 2
+  - - 3
+members:
+  - name: pg1
+    tags:
+      - a
+      - b
+  - name: pg2
+    tags: []
+"
+        );
+    }
+}
@@ -1,3 +0,0 @@
-pub mod cluster;
-pub mod logs;
-pub mod overview;
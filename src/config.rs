@@ -1,13 +1,79 @@
-use clap::Parser;
+use crate::cli::Command;
+use crate::patroni::patroni::ClientOptions;
+use crate::patroni::tls::TlsOptions;
+use crate::services::alerts::AlertSink;
+use anyhow::{Context, anyhow, bail};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+/// System-wide configuration file, used when the user has none
+const SYSTEM_CONFIG_FILE: &str = "/etc/clup.toml";
+
 /// clup - CLI application for monitoring a Patroni PostgreSQL cluster
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Config {
-    /// Patroni API address
-    #[arg(short, long, default_value = "127.0.0.1:8008")]
-    pub patroni_addr: String,
+    /// Configuration file [default: ~/.config/clup/config.toml, then /etc/clup.toml]
+    #[arg(long, env = "CLUP_CONFIG")]
+    pub config_file: Option<PathBuf>,
+
+    /// Profile of the configuration file to use instead of its default_profile
+    #[arg(long, env = "CLUP_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Profiles to watch on the multi-cluster dashboard, or `all`; flags given
+    /// on the command line apply to every one of them
+    #[arg(long, env = "CLUP_CLUSTERS", value_delimiter = ',')]
+    pub clusters: Vec<String>,
+
+    /// Patroni API address; repeat or comma-separate to list several cluster members
+    #[arg(short, long, default_value = "127.0.0.1:8008", value_delimiter = ',')]
+    pub patroni_addr: Vec<String>,
+
+    /// Patroni API request timeout in milliseconds
+    #[arg(long, default_value = "3000", value_parser = clap::value_parser!(u64).range(100..))]
+    pub patroni_timeout_ms: u64,
+
+    /// Timeout in milliseconds of Patroni API requests changing the cluster,
+    /// which Patroni answers once a switchover or restart has completed
+    #[arg(long, default_value = "120000", value_parser = clap::value_parser!(u64).range(100..))]
+    pub patroni_write_timeout_ms: u64,
+
+    /// Use HTTPS for the Patroni REST API (implied by the other TLS options)
+    #[arg(long)]
+    pub patroni_https: bool,
+
+    /// CA bundle (PEM) used to verify the Patroni REST API certificate
+    #[arg(long)]
+    pub patroni_ca_file: Option<PathBuf>,
+
+    /// Client certificate (PEM) presented to the Patroni REST API
+    #[arg(long, requires = "patroni_key_file")]
+    pub patroni_cert_file: Option<PathBuf>,
+
+    /// Private key (PEM) of the client certificate
+    #[arg(long, requires = "patroni_cert_file")]
+    pub patroni_key_file: Option<PathBuf>,
+
+    /// Do not verify the Patroni REST API certificate
+    #[arg(long)]
+    pub patroni_insecure: bool,
+
+    /// Username for Patroni REST API basic authentication
+    #[arg(long, env = "CLUP_PATRONI_USERNAME")]
+    pub patroni_username: Option<String>,
+
+    /// Password for Patroni REST API basic authentication
+    #[arg(long, env = "CLUP_PATRONI_PASSWORD", hide_env_values = true)]
+    pub patroni_password: Option<String>,
+
+    /// File with the password for Patroni REST API basic authentication
+    #[arg(long)]
+    pub patroni_password_file: Option<PathBuf>,
 
     /// DNS server for IP detection
     #[arg(long, default_value = "8.8.8.8:80")]
@@ -24,12 +90,136 @@ pub struct Config {
     /// Services to monitor (comma-separated)
     #[arg(long, default_value = "patroni,haproxy,pgbouncer,keepalived")]
     pub services: String,
+
+    /// Patroni polling interval in milliseconds
+    #[arg(long, default_value = "2000", value_parser = clap::value_parser!(u64).range(100..))]
+    pub patroni_interval_ms: u64,
+
+    /// Services, HAProxy and VIP polling interval in milliseconds
+    #[arg(long, default_value = "5000", value_parser = clap::value_parser!(u64).range(100..))]
+    pub services_interval_ms: u64,
+
+    /// Logs polling interval in milliseconds
+    #[arg(long, default_value = "3000", value_parser = clap::value_parser!(u64).range(100..))]
+    pub logs_interval_ms: u64,
+
+    /// Shell command run on every alert, with the event as JSON on stdin (repeatable)
+    #[arg(long)]
+    pub alert_command: Vec<String>,
+
+    /// URL every alert is POSTed to as JSON (repeatable)
+    #[arg(long)]
+    pub alert_webhook: Vec<String>,
+
+    /// File every alert is appended to as a JSON line (repeatable)
+    #[arg(long)]
+    pub alert_file: Vec<PathBuf>,
+
+    /// JSON-lines file recording every action [default: ~/.local/state/clup/audit.jsonl]
+    #[arg(long, env = "CLUP_AUDIT_FILE")]
+    pub audit_file: Option<PathBuf>,
+
+    /// Run a single command and exit instead of starting the TUI
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Settings of every profile named by `--clusters`
+    #[arg(skip)]
+    pub cluster_profiles: Vec<ClusterProfile>,
+
+    /// Profile the settings come from, `--profile` or the file's default_profile
+    #[arg(skip)]
+    pub resolved_profile: Option<String>,
+}
+
+/// A cluster of the multi-cluster dashboard with the settings of its profile
+#[derive(Debug, Clone)]
+pub struct ClusterProfile {
+    pub name: String,
+    pub config: Config,
 }
 
 impl Config {
-    /// Parse command-line arguments into Config
-    pub fn new() -> Self {
-        Config::parse()
+    /// Parse command-line arguments into Config, filling in settings that
+    /// were not given on the command line or in the environment from the
+    /// configuration file
+    pub fn new() -> anyhow::Result<Self> {
+        let matches = Config::command().get_matches();
+        let base = Config::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+        let path = match &base.config_file {
+            Some(path) => Some(path.clone()),
+            None => default_config_file(),
+        };
+        let file = match path {
+            Some(path) => Some((ConfigFile::load(&path)?, path)),
+            None => None,
+        };
+
+        let mut config = base.with_profile(file.as_ref(), base.profile.as_deref(), &matches)?;
+        if !base.clusters.is_empty() {
+            let Some((config_file, _)) = &file else {
+                bail!("--clusters names profiles but no configuration file was found");
+            };
+            let names = if base.clusters == ["all"] {
+                config_file.profiles.keys().cloned().collect()
+            } else {
+                base.clusters.clone()
+            };
+
+            for name in names {
+                config.cluster_profiles.push(ClusterProfile {
+                    config: base.with_profile(file.as_ref(), Some(&name), &matches)?,
+                    name,
+                });
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// A copy with the settings of `profile` (or the file's default profile)
+    /// applied where no flag was given
+    fn with_profile(
+        &self,
+        file: Option<&(ConfigFile, PathBuf)>,
+        profile: Option<&str>,
+        matches: &ArgMatches,
+    ) -> anyhow::Result<Config> {
+        let mut config = self.clone();
+        match file {
+            Some((file, path)) => {
+                file.settings(profile)
+                    .with_context(|| format!("Invalid configuration file {}", path.display()))?
+                    .apply(&mut config, matches);
+                config.resolved_profile = profile.or(file.default_profile.as_deref()).map(str::to_string);
+            }
+            None => {
+                if let Some(profile) = profile {
+                    bail!("Profile {profile} requested but no configuration file was found");
+                }
+            }
+        }
+
+        for (name, value) in [
+            ("patroni_timeout_ms", config.patroni_timeout_ms),
+            ("patroni_write_timeout_ms", config.patroni_write_timeout_ms),
+            ("patroni_interval_ms", config.patroni_interval_ms),
+            ("services_interval_ms", config.services_interval_ms),
+            ("logs_interval_ms", config.logs_interval_ms),
+        ] {
+            if value < 100 {
+                bail!("{name} must be at least 100, got {value}");
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Name of the cluster in audit records and alerts: its profile, or
+    /// `default` without one
+    pub fn cluster_name(&self) -> &str {
+        self.resolved_profile.as_deref().unwrap_or("default")
     }
 
     /// Get the maximum replication lag in microseconds
@@ -42,6 +232,319 @@ impl Config {
         self.services
             .split(',')
             .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
             .collect()
     }
-}
\ No newline at end of file
+
+    /// Get the Patroni API request timeout
+    pub fn patroni_timeout(&self) -> Duration {
+        Duration::from_millis(self.patroni_timeout_ms)
+    }
+
+    /// Get the timeout of Patroni API requests changing the cluster
+    pub fn patroni_write_timeout(&self) -> Duration {
+        Duration::from_millis(self.patroni_write_timeout_ms)
+    }
+
+    /// Build the Patroni REST API client options, reading the password file if given
+    pub fn patroni_client_options(&self) -> anyhow::Result<ClientOptions> {
+        let password = match (&self.patroni_password, &self.patroni_password_file) {
+            (Some(password), _) => Some(password.clone()),
+            (None, Some(path)) => Some(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read password file {}", path.display()))?
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string(),
+            ),
+            (None, None) => None,
+        };
+
+        let tls = TlsOptions {
+            ca_file: self.patroni_ca_file.clone(),
+            cert_file: self.patroni_cert_file.clone(),
+            key_file: self.patroni_key_file.clone(),
+            insecure: self.patroni_insecure,
+        };
+
+        Ok(ClientOptions {
+            timeout: self.patroni_timeout(),
+            write_timeout: self.patroni_write_timeout(),
+            https: self.patroni_https
+                || tls.ca_file.is_some()
+                || tls.cert_file.is_some()
+                || tls.insecure,
+            tls,
+            username: self.patroni_username.clone(),
+            password,
+        })
+    }
+
+    /// Get the audit log file: the configured one, otherwise the one in the
+    /// user's state directory (honouring `XDG_STATE_HOME`)
+    pub fn audit_log_path(&self) -> Option<PathBuf> {
+        self.audit_file.clone().or_else(|| {
+            std::env::var_os("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state")))
+                .map(|dir| dir.join("clup").join("audit.jsonl"))
+        })
+    }
+
+    /// Get the configured alert sinks
+    pub fn alert_sinks(&self) -> Vec<AlertSink> {
+        let commands = self.alert_command.iter().cloned().map(AlertSink::Command);
+        let webhooks = self.alert_webhook.iter().cloned().map(AlertSink::Webhook);
+        let files = self.alert_file.iter().cloned().map(AlertSink::File);
+
+        commands.chain(webhooks).chain(files).collect()
+    }
+
+    /// Get the Patroni polling interval
+    pub fn patroni_interval(&self) -> Duration {
+        Duration::from_millis(self.patroni_interval_ms)
+    }
+
+    /// Get the services polling interval
+    pub fn services_interval(&self) -> Duration {
+        Duration::from_millis(self.services_interval_ms)
+    }
+
+    /// Get the logs polling interval
+    pub fn logs_interval(&self) -> Duration {
+        Duration::from_millis(self.logs_interval_ms)
+    }
+}
+
+/// `~/.config/clup/config.toml` (honouring `XDG_CONFIG_HOME`) if it exists,
+/// otherwise `/etc/clup.toml` if that exists
+fn default_config_file() -> Option<PathBuf> {
+    let user_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+
+    user_dir
+        .map(|dir| dir.join("clup").join("config.toml"))
+        .into_iter()
+        .chain([PathBuf::from(SYSTEM_CONFIG_FILE)])
+        .find(|path| path.is_file())
+}
+
+/// Contents of the TOML configuration file:
+///
+/// ```toml
+/// default_profile = "staging"
+///
+/// [defaults]
+/// patroni_username = "admin"
+///
+/// [profiles.staging]
+/// patroni_addr = ["10.0.1.1:8008", "10.0.1.2:8008"]
+/// services = ["patroni", "haproxy"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    /// Profile used when `--profile` is not given
+    default_profile: Option<String>,
+    /// Settings shared by all profiles
+    #[serde(default)]
+    defaults: FileSettings,
+    #[serde(default)]
+    profiles: BTreeMap<String, FileSettings>,
+}
+
+/// Settings of the configuration file; keys are named after the flags
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileSettings {
+    patroni_addr: Option<Vec<String>>,
+    patroni_timeout_ms: Option<u64>,
+    patroni_write_timeout_ms: Option<u64>,
+    patroni_https: Option<bool>,
+    patroni_ca_file: Option<PathBuf>,
+    patroni_cert_file: Option<PathBuf>,
+    patroni_key_file: Option<PathBuf>,
+    patroni_insecure: Option<bool>,
+    patroni_username: Option<String>,
+    patroni_password: Option<String>,
+    patroni_password_file: Option<PathBuf>,
+    dns_server: Option<String>,
+    haproxy_socket: Option<String>,
+    max_replication_lag_secs: Option<u64>,
+    services: Option<Vec<String>>,
+    patroni_interval_ms: Option<u64>,
+    services_interval_ms: Option<u64>,
+    logs_interval_ms: Option<u64>,
+    alert_command: Option<Vec<String>>,
+    alert_webhook: Option<Vec<String>>,
+    alert_file: Option<Vec<PathBuf>>,
+    audit_file: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read configuration file {}", path.display()))?;
+
+        toml::from_str(&text).with_context(|| format!("Invalid configuration file {}", path.display()))
+    }
+
+    /// The defaults overlaid with the requested profile, or with the default
+    /// profile if none is requested
+    fn settings(&self, profile: Option<&str>) -> anyhow::Result<FileSettings> {
+        let Some(name) = profile.or(self.default_profile.as_deref()) else {
+            return Ok(self.defaults.clone());
+        };
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            anyhow!("Unknown profile {name}, known profiles: {}", known.join(", "))
+        })?;
+
+        Ok(profile.clone().or(self.defaults.clone()))
+    }
+}
+
+impl FileSettings {
+    /// Fill the settings missing from `self` with those of `fallback`
+    fn or(self, fallback: FileSettings) -> FileSettings {
+        FileSettings {
+            patroni_addr: self.patroni_addr.or(fallback.patroni_addr),
+            patroni_timeout_ms: self.patroni_timeout_ms.or(fallback.patroni_timeout_ms),
+            patroni_write_timeout_ms: self.patroni_write_timeout_ms.or(fallback.patroni_write_timeout_ms),
+            patroni_https: self.patroni_https.or(fallback.patroni_https),
+            patroni_ca_file: self.patroni_ca_file.or(fallback.patroni_ca_file),
+            patroni_cert_file: self.patroni_cert_file.or(fallback.patroni_cert_file),
+            patroni_key_file: self.patroni_key_file.or(fallback.patroni_key_file),
+            patroni_insecure: self.patroni_insecure.or(fallback.patroni_insecure),
+            patroni_username: self.patroni_username.or(fallback.patroni_username),
+            patroni_password: self.patroni_password.or(fallback.patroni_password),
+            patroni_password_file: self.patroni_password_file.or(fallback.patroni_password_file),
+            dns_server: self.dns_server.or(fallback.dns_server),
+            haproxy_socket: self.haproxy_socket.or(fallback.haproxy_socket),
+            max_replication_lag_secs: self.max_replication_lag_secs.or(fallback.max_replication_lag_secs),
+            services: self.services.or(fallback.services),
+            patroni_interval_ms: self.patroni_interval_ms.or(fallback.patroni_interval_ms),
+            services_interval_ms: self.services_interval_ms.or(fallback.services_interval_ms),
+            logs_interval_ms: self.logs_interval_ms.or(fallback.logs_interval_ms),
+            alert_command: self.alert_command.or(fallback.alert_command),
+            alert_webhook: self.alert_webhook.or(fallback.alert_webhook),
+            alert_file: self.alert_file.or(fallback.alert_file),
+            audit_file: self.audit_file.or(fallback.audit_file),
+        }
+    }
+
+    /// Copy the settings into `config` unless the flag was given on the
+    /// command line or through its environment variable
+    fn apply(self, config: &mut Config, matches: &ArgMatches) {
+        let explicit = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+            )
+        };
+
+        macro_rules! apply {
+            ($($field:ident),* $(,)?) => {
+                $(if let Some(value) = self.$field && !explicit(stringify!($field)) {
+                    config.$field = value.into();
+                })*
+            };
+        }
+
+        apply!(
+            patroni_addr,
+            patroni_timeout_ms,
+            patroni_write_timeout_ms,
+            patroni_https,
+            patroni_ca_file,
+            patroni_cert_file,
+            patroni_key_file,
+            patroni_insecure,
+            patroni_username,
+            patroni_password,
+            patroni_password_file,
+            dns_server,
+            haproxy_socket,
+            max_replication_lag_secs,
+            patroni_interval_ms,
+            services_interval_ms,
+            logs_interval_ms,
+            alert_command,
+            alert_webhook,
+            alert_file,
+            audit_file,
+        );
+        if let Some(services) = self.services
+            && !explicit("services")
+        {
+            config.services = services.join(",");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+        [defaults]
+        patroni_timeout_ms = 5000
+        haproxy_socket = "/run/haproxy.sock"
+        services = ["patroni", "haproxy"]
+
+        [profiles.prod]
+        patroni_timeout_ms = 7000
+        patroni_addr = ["10.0.0.1:8008", "10.0.0.2:8008"]
+    "#;
+
+    fn file() -> (ConfigFile, PathBuf) {
+        (toml::from_str(FILE).unwrap(), PathBuf::from("clup.toml"))
+    }
+
+    /// Config for the command line `args` with the `prod` profile applied
+    fn with_prod(args: &[&str]) -> Config {
+        let matches = Config::command().get_matches_from(std::iter::once("clup").chain(args.iter().copied()));
+        let base = Config::from_arg_matches(&matches).unwrap();
+        base.with_profile(Some(&file()), Some("prod"), &matches).unwrap()
+    }
+
+    #[test]
+    fn profile_overrides_the_base_file() {
+        let settings = file().0.settings(Some("prod")).unwrap();
+
+        assert_eq!(settings.patroni_timeout_ms, Some(7000));
+        assert_eq!(settings.haproxy_socket.as_deref(), Some("/run/haproxy.sock"));
+        assert_eq!(settings.services, Some(vec!["patroni".to_string(), "haproxy".to_string()]));
+    }
+
+    #[test]
+    fn explicit_flag_beats_the_profile() {
+        let config = with_prod(&["--patroni-timeout-ms", "4000", "--patroni-addr", "127.0.0.1:8008"]);
+
+        assert_eq!(config.patroni_timeout_ms, 4000);
+        assert_eq!(config.patroni_addr, ["127.0.0.1:8008"]);
+    }
+
+    #[test]
+    fn default_flag_keeps_the_file_value() {
+        let config = with_prod(&[]);
+
+        assert_eq!(config.patroni_timeout_ms, 7000);
+        assert_eq!(config.patroni_addr, ["10.0.0.1:8008", "10.0.0.2:8008"]);
+        assert_eq!(config.haproxy_socket, "/run/haproxy.sock");
+        assert_eq!(config.services, "patroni,haproxy");
+        // Not in the file, the flag's default stays
+        assert_eq!(config.patroni_write_timeout_ms, 120000);
+    }
+
+    #[test]
+    fn cluster_name_follows_the_default_profile() {
+        let mut file = file();
+        file.0.default_profile = Some("prod".to_string());
+        let matches = Config::command().get_matches_from(["clup"]);
+        let base = Config::from_arg_matches(&matches).unwrap();
+
+        assert_eq!(base.with_profile(Some(&file), None, &matches).unwrap().cluster_name(), "prod");
+        assert_eq!(base.with_profile(None, None, &matches).unwrap().cluster_name(), "default");
+    }
+}
@@ -1,5 +1,5 @@
 mod app;
-mod components;
+mod cli;
 mod config;
 mod patroni;
 mod services;
@@ -12,12 +12,20 @@ use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
-use std::io::{Result, stdout};
+use std::io::stdout;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> anyhow::Result<()> {
     // Parse command-line arguments
-    let config = Config::new();
+    let config = Config::new()?;
+
+    if let Some(command) = &config.command {
+        let code = cli::run(&config, command).await;
+        std::process::exit(code);
+    }
+
+    // Build the app before touching the terminal so configuration errors stay readable
+    let mut app = App::new(config)?;
 
     enable_raw_mode()?;
     let mut stdout = stdout();
@@ -26,7 +34,6 @@ async fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(config);
     app.run(&mut terminal).await.expect("failed to run");
 
     disable_raw_mode()?;
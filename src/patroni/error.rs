@@ -0,0 +1,128 @@
+use std::fmt;
+use std::io;
+
+/// Failure of a Patroni REST API call
+#[derive(Debug, Clone)]
+pub enum PatroniError {
+    /// The endpoint actively refused the connection
+    ConnectionRefused { url: String },
+    /// Connecting or reading the response took longer than the configured timeout
+    Timeout { url: String },
+    /// The host name could not be resolved
+    Dns { url: String, message: String },
+    /// Any other network or TLS failure
+    Transport { url: String, message: String },
+    /// The endpoint answered with a non-success HTTP status
+    Http { url: String, status: u16, body: String },
+    /// The response could not be decoded into the expected structure
+    Schema { url: String, message: String },
+}
+
+impl PatroniError {
+    /// Build a schema error for a response of `url` that failed to decode
+    pub fn schema(url: &str, err: impl fmt::Display) -> Self {
+        PatroniError::Schema {
+            url: url.to_string(),
+            message: err.to_string(),
+        }
+    }
+
+    /// URL of the request that failed
+    pub fn url(&self) -> &str {
+        match self {
+            PatroniError::ConnectionRefused { url }
+            | PatroniError::Timeout { url }
+            | PatroniError::Dns { url, .. }
+            | PatroniError::Transport { url, .. }
+            | PatroniError::Http { url, .. }
+            | PatroniError::Schema { url, .. } => url,
+        }
+    }
+
+    /// HTTP status of the response, if one was received
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            PatroniError::Http { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Short category of the error, suitable for banners and status columns
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PatroniError::ConnectionRefused { .. } => "connection refused",
+            PatroniError::Timeout { .. } => "timeout",
+            PatroniError::Dns { .. } => "DNS failure",
+            PatroniError::Transport { .. } => "transport error",
+            PatroniError::Http { .. } => "HTTP error",
+            PatroniError::Schema { .. } => "unexpected response",
+        }
+    }
+}
+
+impl fmt::Display for PatroniError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatroniError::ConnectionRefused { url } => write!(f, "connection refused by {url}"),
+            PatroniError::Timeout { url } => write!(f, "timeout waiting for {url}"),
+            PatroniError::Dns { url, message } => write!(f, "cannot resolve {url}: {message}"),
+            PatroniError::Transport { url, message } => write!(f, "{url}: {message}"),
+            PatroniError::Http { url, status, body } if body.is_empty() => {
+                write!(f, "HTTP {status} from {url}")
+            }
+            PatroniError::Http { url, status, body } => {
+                write!(f, "HTTP {status} from {url}: {body}")
+            }
+            PatroniError::Schema { url, message } => {
+                write!(f, "unexpected response from {url}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatroniError {}
+
+impl From<Box<ureq::Error>> for PatroniError {
+    fn from(err: Box<ureq::Error>) -> Self {
+        match *err {
+            ureq::Error::Status(status, resp) => {
+                let url = resp.get_url().to_string();
+                let body = resp.into_string().unwrap_or_default().trim().to_string();
+                PatroniError::Http { url, status, body }
+            }
+            ureq::Error::Transport(transport) => {
+                let url = transport.url().map_or_else(String::new, |u| u.to_string());
+                let io_kind = io_error_kind(&transport);
+                // Transport's own Display is prefixed with the URL, which we print separately
+                let message = transport
+                    .to_string()
+                    .trim_start_matches(&format!("{url}: "))
+                    .to_string();
+
+                match (transport.kind(), io_kind) {
+                    (_, Some(io::ErrorKind::ConnectionRefused)) => {
+                        PatroniError::ConnectionRefused { url }
+                    }
+                    (_, Some(io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)) => {
+                        PatroniError::Timeout { url }
+                    }
+                    (ureq::ErrorKind::Dns, _) => PatroniError::Dns { url, message },
+                    _ => PatroniError::Transport { url, message },
+                }
+            }
+        }
+    }
+}
+
+/// Find the underlying I/O error kind of a transport failure, if any
+fn io_error_kind(transport: &ureq::Transport) -> Option<io::ErrorKind> {
+    let mut source = std::error::Error::source(transport);
+    while let Some(err) = source {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            return Some(io_err.kind());
+        }
+        source = err.source();
+    }
+
+    None
+}
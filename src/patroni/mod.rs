@@ -1 +1,7 @@
+pub mod error;
+#[allow(clippy::module_inception)]
 pub mod patroni;
+pub mod tls;
+/// Cluster states for unit tests
+#[cfg(test)]
+pub mod testing;
@@ -1,26 +1,81 @@
-use serde::Deserialize;
+use crate::patroni::error::PatroniError;
+use crate::patroni::tls::{self, TlsOptions};
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+/// Connection settings for the Patroni REST API
 #[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    pub timeout: Duration,
+    /// Timeout of requests changing the cluster; Patroni answers switchovers
+    /// and restarts only once they are done
+    pub write_timeout: Duration,
+    pub https: bool,
+    pub tls: TlsOptions,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Patroni REST API client.
+///
+/// Knows the REST endpoints of all cluster members (configured ones plus those
+/// discovered from `/cluster`) and transparently fails over to another member
+/// when the active endpoint is unreachable.
+#[derive(Debug, Clone)]
 pub struct Patroni {
-    pub addr: String,
+    agent: ureq::Agent,
+    /// Agent with the longer timeout used for all but `GET` requests
+    write_agent: ureq::Agent,
+    scheme: &'static str,
+    auth_header: Option<String>,
+    endpoints: Arc<Mutex<Endpoints>>,
+}
+
+#[derive(Debug, Default)]
+struct Endpoints {
+    configured: Vec<String>,
+    discovered: Vec<String>,
+    active: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct ClusterInfo {
     pub scope: String,
     pub node_name: String,
-    pub leader_node_name: String,
+    /// `None` while no member holds the leader lock
+    pub leader_node_name: Option<String>,
     pub members: Vec<NodeStatus>,
     pub members_map: HashMap<String, NodeStatus>,
     pub patroni_data: PatroniData,
+    /// REST endpoint the data was fetched from
+    pub api_endpoint: String,
+    /// Whether the endpoint is not the first configured one
+    pub api_fallback: bool,
+    /// Switchover queued with `scheduled_at`, if any
+    pub scheduled_switchover: Option<ScheduledSwitchover>,
+    /// Whether automatic failover is paused (maintenance mode)
+    pub paused: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct NodesStatus {
-    pub scope: String,
     pub members: Vec<NodeStatus>,
+    pub scheduled_switchover: Option<ScheduledSwitchover>,
+    #[serde(default)]
+    pub pause: bool,
+}
+
+/// Pending switchover as reported by `/cluster`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduledSwitchover {
+    pub at: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -29,7 +84,106 @@ pub struct NodeStatus {
     pub role: String,
     pub state: String,
     pub host: String,
+    pub port: Option<u16>,
+    pub timeline: Option<u64>,
+    pub api_url: Option<String>,
+    /// Replication lag; Patroni reports `"unknown"` when it cannot be determined
+    #[serde(default, deserialize_with = "deserialize_lag")]
     pub lag: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_lag")]
+    pub receive_lag: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_lag")]
+    pub replay_lag: Option<u64>,
+    pub lsn: Option<String>,
+    pub receive_lsn: Option<String>,
+    pub replay_lsn: Option<String>,
+    #[serde(default)]
+    pub pending_restart: bool,
+    /// Changed parameters requiring a restart, keyed by parameter name
+    #[serde(default)]
+    pub pending_restart_reason: BTreeMap<String, Value>,
+    pub scheduled_restart: Option<ScheduledRestart>,
+    #[serde(default)]
+    pub tags: NodeTags,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduledRestart {
+    pub schedule: String,
+    pub postgres_version: Option<String>,
+}
+
+/// Member tags from `patroni.yml`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NodeTags {
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub nofailover: bool,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub noloadbalance: bool,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub clonefrom: bool,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub nosync: bool,
+    pub replicatefrom: Option<String>,
+    /// Custom tags not interpreted by Patroni
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+/// Status reported by a single member on its own `/patroni` endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberStatus {
+    pub state: Option<String>,
+    pub role: Option<String>,
+    pub postmaster_start_time: Option<String>,
+    /// PostgreSQL version number, e.g. `160002`
+    pub server_version: Option<u64>,
+    pub timeline: Option<u64>,
+    #[serde(default)]
+    pub xlog: XlogStatus,
+    /// Whether the cluster is in maintenance mode
+    #[serde(default)]
+    pub pause: bool,
+    /// Unix time of the last successful DCS access
+    pub dcs_last_seen: Option<u64>,
+    #[serde(default)]
+    pub tags: NodeTags,
+    #[serde(default)]
+    pub pending_restart: bool,
+    #[serde(default)]
+    pub pending_restart_reason: BTreeMap<String, Value>,
+    pub replication_state: Option<String>,
+    pub patroni: Option<PatroniVersion>,
+}
+
+/// WAL positions: `location` on the leader, received/replayed positions on replicas
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct XlogStatus {
+    pub location: Option<u64>,
+    pub received_location: Option<u64>,
+    pub replayed_location: Option<u64>,
+    pub replayed_timestamp: Option<String>,
+    /// Whether WAL replay is paused
+    #[serde(default)]
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PatroniVersion {
+    pub version: Option<String>,
+}
+
+/// Timeline switch from `/history`
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub timeline: u64,
+    /// WAL position where the timeline ended
+    pub lsn: u64,
+    pub reason: String,
+    /// Not reported by old Patroni versions
+    pub timestamp: Option<String>,
+    /// Not reported by old Patroni versions
+    pub new_leader: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,74 +195,240 @@ pub struct PatroniData {
 }
 
 impl Patroni {
-    pub fn new(addr: String) -> Self {
-        Patroni { addr }
+    pub fn new(addrs: Vec<String>, options: &ClientOptions) -> anyhow::Result<Self> {
+        let mut agent = ureq::AgentBuilder::new()
+            .timeout_connect(options.timeout)
+            .timeout(options.timeout);
+        let mut write_agent = ureq::AgentBuilder::new()
+            .timeout_connect(options.timeout)
+            .timeout(options.write_timeout);
+        if options.https {
+            let tls_config = tls::client_config(&options.tls)?;
+            agent = agent.tls_config(tls_config.clone());
+            write_agent = write_agent.tls_config(tls_config);
+        }
+
+        // Basic auth is sent with every request; Patroni only checks it on write endpoints
+        let auth_header = options.username.as_ref().map(|username| {
+            let credentials = format!("{}:{}", username, options.password.as_deref().unwrap_or(""));
+            format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials))
+        });
+
+        // Addresses may be given as URLs; the scheme is decided by the options
+        let addrs: Vec<String> = addrs
+            .iter()
+            .filter_map(|addr| addr_from_api_url(addr))
+            .collect();
+        let active = addrs.first().cloned().unwrap_or_default();
+
+        Ok(Patroni {
+            agent: agent.build(),
+            write_agent: write_agent.build(),
+            scheme: if options.https { "https" } else { "http" },
+            auth_header,
+            endpoints: Arc::new(Mutex::new(Endpoints {
+                configured: addrs,
+                discovered: vec![],
+                active,
+            })),
+        })
+    }
+
+    /// Address of the endpoint currently used for requests
+    pub fn active_addr(&self) -> String {
+        self.lock_endpoints().active.clone()
+    }
+
+    /// All known endpoints: the active one first, then configured, then discovered
+    pub fn endpoints(&self) -> Vec<String> {
+        let endpoints = self.lock_endpoints();
+        let mut all = vec![endpoints.active.clone()];
+        for addr in endpoints.configured.iter().chain(&endpoints.discovered) {
+            if !all.contains(addr) {
+                all.push(addr.clone());
+            }
+        }
+
+        all
+    }
+
+    /// Send a request to the active endpoint, trying the other known endpoints
+    /// in turn when it cannot be reached. The first endpoint that answers (even
+    /// with an HTTP error status) becomes the active one. Requests other than
+    /// `GET` are only sent again when they cannot have reached the endpoint,
+    /// a switchover must not be repeated because its answer was slow.
+    pub fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let mut last_err = None;
+
+        for addr in self.endpoints() {
+            match self.send_to(&addr, method, path, body) {
+                Err(e) if can_retry(&e, method) => last_err = Some(e),
+                result => {
+                    self.lock_endpoints().active = addr;
+                    return result;
+                }
+            }
+        }
+
+        Err(last_err.expect("at least one Patroni endpoint is known"))
+    }
+
+    /// Send a request to the given endpoint only, without failover
+    pub fn send_to(
+        &self,
+        addr: &str,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let agent = if method == "GET" { &self.agent } else { &self.write_agent };
+        let mut request = agent.request(method, &(self.url_for(addr) + path));
+        if let Some(auth_header) = &self.auth_header {
+            request = request.set("Authorization", auth_header);
+        }
+
+        match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(body),
+            None => request.call(),
+        }
+        .map_err(Box::new)
+    }
+
+    /// Send a request to the member behind `api_url`. Per-node operations
+    /// such as restarts must reach that member rather than any endpoint.
+    pub fn send_to_member(
+        &self,
+        api_url: &str,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<ureq::Response, Box<ureq::Error>> {
+        let addr = addr_from_api_url(api_url).unwrap_or_else(|| api_url.to_string());
+        self.send_to(&addr, method, path, body)
+    }
+
+    fn url_for(&self, addr: &str) -> String {
+        format!("{}://{}", self.scheme, addr)
     }
 
-    pub fn base_url(&self) -> String {
-        "http://".to_string() + self.addr.clone().as_str()
+    fn lock_endpoints(&self) -> std::sync::MutexGuard<'_, Endpoints> {
+        self.endpoints.lock().unwrap_or_else(|e| e.into_inner())
     }
 
-    pub fn get_cluster_info(&self) -> ClusterInfo {
-        let nodes = self.get_cluster_nodes();
+    /// Remember the REST endpoints advertised by cluster members
+    fn discover_endpoints(&self, nodes: &[NodeStatus]) {
+        let discovered = nodes
+            .iter()
+            .filter_map(|n| n.api_url.as_deref().and_then(addr_from_api_url))
+            .collect();
+
+        self.lock_endpoints().discovered = discovered;
+    }
+
+    /// GET `path` with failover and decode the JSON response
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, PatroniError> {
+        let resp = self.send("GET", path, None)?;
+        let url = resp.get_url().to_string();
+
+        resp.into_json().map_err(|e| PatroniError::schema(&url, e))
+    }
+
+    pub fn get_cluster_info(&self) -> Result<ClusterInfo, PatroniError> {
+        let status = self.get_cluster_status()?;
+        let nodes = status.members;
         let mut members = HashMap::new();
-        let mut leader_node_name: String = "-".to_string();
+        let mut leader_node_name = None;
 
         for node in nodes.clone() {
             members.insert(node.name.clone(), node.clone());
             if node.role == "leader" {
-                leader_node_name = node.name.clone();
+                leader_node_name = Some(node.name.clone());
             }
         }
 
-        let patroni_info = self.get_patroni_info();
+        let patroni_info = self.get_patroni_info()?;
+        let api_endpoint = self.active_addr();
+        let api_fallback = self
+            .lock_endpoints()
+            .configured
+            .first()
+            .is_some_and(|addr| *addr != api_endpoint);
 
-        ClusterInfo {
+        Ok(ClusterInfo {
             scope: patroni_info.scope.clone(),
             node_name: patroni_info.node_name.clone(),
-            leader_node_name: leader_node_name,
+            leader_node_name,
             members: nodes,
             members_map: members,
             patroni_data: patroni_info,
-        }
+            api_endpoint,
+            api_fallback,
+            scheduled_switchover: status.scheduled_switchover,
+            paused: status.pause,
+        })
     }
 
-    pub fn get_cluster_nodes(&self) -> Vec<NodeStatus> {
-        let url = self.base_url() + "/cluster";
-        let resp = ureq::get(url.as_str()).call();
+    /// Fetch `/cluster` with members sorted by name
+    fn get_cluster_status(&self) -> Result<NodesStatus, PatroniError> {
+        let mut data: NodesStatus = self.get_json("/cluster")?;
+        data.members.sort_by_key(|node| node.name.clone());
+        self.discover_endpoints(&data.members);
 
-        if let Ok(response) = resp {
-            if let Ok(mut data) = response.into_json::<NodesStatus>() {
-                data.members.sort_by_key(|node| node.name.clone());
+        Ok(data)
+    }
 
-                return data.members;
-            }
-        }
+    /// Fetch a member's own `/patroni` status through its `api_url`
+    pub fn get_member_status(&self, api_url: &str) -> Result<MemberStatus, PatroniError> {
+        let resp = self.send_to_member(api_url, "GET", "/patroni", None)?;
+        let url = resp.get_url().to_string();
 
-        vec![]
+        resp.into_json().map_err(|e| PatroniError::schema(&url, e))
     }
 
-    pub fn get_patroni_info(&self) -> PatroniData {
-        match ureq::get(self.base_url().as_str()).call() {
-            Ok(resp) => {
-                if let Ok(json) = resp.into_json::<Value>() {
-                    return self.parse_patroni_json(json);
-                }
-            }
-            Err(ureq::Error::Status(503, resp)) => {
-                if let Ok(json) = resp.into_json::<Value>() {
-                    return self.parse_patroni_json(json);
+    /// Fetch the timeline history, oldest switch first
+    pub fn get_history(&self) -> Result<Vec<HistoryEntry>, PatroniError> {
+        // Entries are arrays: [timeline, lsn, reason, timestamp, new_leader]
+        let rows: Vec<Vec<Value>> = self.get_json("/history")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let text = |i: usize| row.get(i).and_then(Value::as_str).map(str::to_string);
+                HistoryEntry {
+                    timeline: row.first().and_then(Value::as_u64).unwrap_or(0),
+                    lsn: row.get(1).and_then(Value::as_u64).unwrap_or(0),
+                    reason: text(2).unwrap_or_default(),
+                    timestamp: text(3),
+                    new_leader: text(4),
                 }
-            }
-            Err(_) => {}
-        }
+            })
+            .collect())
+    }
 
-        PatroniData {
-            role: "-".to_string(),
-            state: "-".to_string(),
-            scope: "-".to_string(),
-            node_name: "-".to_string(),
-        }
+    /// Fetch the dynamic cluster configuration stored in the DCS
+    pub fn get_config(&self) -> Result<Value, PatroniError> {
+        self.get_json("/config")
+    }
+
+    pub fn get_patroni_info(&self) -> Result<PatroniData, PatroniError> {
+        let resp = match self.send("GET", "/", None).map_err(|e| *e) {
+            // Replicas answer `/` with 503 but still report their status
+            Ok(resp) | Err(ureq::Error::Status(503, resp)) => resp,
+            Err(e) => return Err(Box::new(e).into()),
+        };
+        let url = resp.get_url().to_string();
+        let json = resp
+            .into_json::<Value>()
+            .map_err(|e| PatroniError::schema(&url, e))?;
+
+        Ok(self.parse_patroni_json(json))
     }
 
     pub fn parse_patroni_json(&self, json: Value) -> PatroniData {
@@ -121,15 +441,112 @@ impl Patroni {
     }
 }
 
+impl NodeTags {
+    /// Names of the tags that are set, e.g. `nofailover` or `replicatefrom=pg2`
+    pub fn labels(&self) -> Vec<String> {
+        let flags = [
+            (self.nofailover, "nofailover"),
+            (self.noloadbalance, "noloadbalance"),
+            (self.clonefrom, "clonefrom"),
+            (self.nosync, "nosync"),
+        ];
+        let mut labels: Vec<String> = flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| name.to_string())
+            .collect();
+
+        if let Some(source) = &self.replicatefrom {
+            labels.push(format!("replicatefrom={source}"));
+        }
+        for (name, value) in &self.other {
+            let value = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+            labels.push(format!("{name}={value}"));
+        }
+
+        labels
+    }
+}
+
 impl ClusterInfo {
+    /// The member currently holding the leader lock
+    pub fn leader(&self) -> Option<&NodeStatus> {
+        self.leader_node_name.as_ref().and_then(|name| self.members_map.get(name))
+    }
+
+    /// Whether the member named `name` holds the leader lock
+    pub fn is_leader(&self, name: &str) -> bool {
+        self.leader_node_name.as_deref() == Some(name)
+    }
+
+    /// Whether a leader exists and is running; a manual failover is only
+    /// needed when it is not
+    pub fn has_healthy_leader(&self) -> bool {
+        self.leader().is_some_and(|n| n.state == "running")
+    }
+
+    /// Replicas that may be promoted by a manual failover, least lagging first.
+    /// Members tagged `nofailover` or not running/streaming are left out.
+    pub fn failover_candidates(&self) -> Vec<&NodeStatus> {
+        let mut candidates: Vec<_> = self
+            .members
+            .iter()
+            .filter(|n| !self.is_leader(&n.name) && !n.tags.nofailover)
+            .filter(|n| matches!(n.state.as_str(), "running" | "streaming"))
+            .collect();
+        // Unknown lag sorts last
+        candidates.sort_by_key(|n| n.lag.unwrap_or(u64::MAX));
+
+        candidates
+    }
+
     pub fn replication_ok(&self, max_lag_us: u64) -> bool {
         self.members
             .iter()
             .filter(|n| n.role != "leader")
             .all(|n| {
                 let state_ok = matches!(n.state.as_str(), "running" | "streaming");
-                let lag_ok   = n.lag.map_or(false, |l| l <= max_lag_us);
+                let lag_ok   = n.lag.is_some_and(|l| l <= max_lag_us);
                 state_ok && lag_ok
             })
     }
 }
+
+/// Whether `send` may try the next endpoint after `err`: always for reads,
+/// for writes only when no connection was made
+fn can_retry(err: &ureq::Error, method: &str) -> bool {
+    match err {
+        ureq::Error::Transport(transport) => {
+            method == "GET" || matches!(transport.kind(), ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed)
+        }
+        ureq::Error::Status(..) => false,
+    }
+}
+
+/// Extract `host:port` from a URL such as a member's `api_url`, e.g. `http://10.0.0.1:8008/patroni`
+fn addr_from_api_url(api_url: &str) -> Option<String> {
+    let rest = api_url.split_once("://").map_or(api_url, |(_, rest)| rest);
+    let addr = rest.split('/').next().unwrap_or("");
+
+    (!addr.is_empty()).then(|| addr.to_string())
+}
+
+/// Accept a lag given as a number, `"unknown"` or `null`
+fn deserialize_lag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Accept a boolean tag the way Patroni does: a boolean, a number or a string
+/// such as `"on"`; anything else counts as unset
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(b) => b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => matches!(s.trim().to_lowercase().as_str(), "true" | "on" | "yes" | "y" | "1"),
+        _ => false,
+    })
+}
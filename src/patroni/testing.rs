@@ -0,0 +1,41 @@
+use crate::patroni::patroni::{ClusterInfo, NodeStatus, PatroniData};
+use serde_json::{Value, json};
+
+/// Member as `/cluster` lists it: a running leader or a streaming replica,
+/// with the fields of `extra` (e.g. `lag`, `tags`) merged in
+pub fn member(name: &str, role: &str, extra: Value) -> NodeStatus {
+    let mut node = json!({
+        "name": name,
+        "role": role,
+        "state": if role == "leader" { "running" } else { "streaming" },
+        "host": "127.0.0.1",
+        "api_url": format!("http://127.0.0.1:8008/{name}"),
+    });
+    if let (Some(node), Value::Object(extra)) = (node.as_object_mut(), extra) {
+        node.extend(extra);
+    }
+
+    serde_json::from_value(node).unwrap()
+}
+
+/// Cluster `demo` of `members`, led by the one with the `leader` role
+pub fn cluster(members: Vec<NodeStatus>) -> ClusterInfo {
+    let leader = members.iter().find(|n| n.role == "leader").map(|n| n.name.clone());
+    ClusterInfo {
+        scope: "demo".to_string(),
+        node_name: "pg1".to_string(),
+        leader_node_name: leader,
+        members_map: members.iter().map(|n| (n.name.clone(), n.clone())).collect(),
+        members,
+        patroni_data: PatroniData {
+            role: "primary".to_string(),
+            state: "running".to_string(),
+            scope: "demo".to_string(),
+            node_name: "pg1".to_string(),
+        },
+        api_endpoint: "127.0.0.1:8008".to_string(),
+        api_fallback: false,
+        scheduled_switchover: None,
+        paused: false,
+    }
+}
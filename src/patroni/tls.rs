@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::WebPkiSupportedAlgorithms;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// TLS settings for HTTPS connections to the Patroni REST API
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// PEM bundle with additional CA certificates to trust
+    pub ca_file: Option<PathBuf>,
+    /// PEM client certificate chain presented to Patroni
+    pub cert_file: Option<PathBuf>,
+    /// PEM private key for the client certificate
+    pub key_file: Option<PathBuf>,
+    /// Skip server certificate verification
+    pub insecure: bool,
+}
+
+/// Build the rustls client configuration for the given options
+pub fn client_config(options: &TlsOptions) -> Result<Arc<ClientConfig>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Failed to set up TLS protocol versions")?;
+
+    let builder = if options.insecure {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification {
+                algorithms: provider.signature_verification_algorithms,
+            }))
+    } else {
+        let mut roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        if let Some(ca_file) = &options.ca_file {
+            for cert in read_certs(ca_file)? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("Invalid CA certificate in {}", ca_file.display()))?;
+            }
+        }
+        builder.with_root_certificates(roots)
+    };
+
+    let config = match (&options.cert_file, &options.key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            let certs = read_certs(cert_file)?;
+            let key = PrivateKeyDer::from_pem_file(key_file)
+                .with_context(|| format!("Failed to read private key {}", key_file.display()))?;
+            builder
+                .with_client_auth_cert(certs, key)
+                .context("Invalid client certificate or key")?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => anyhow::bail!("Client certificate and key must be given together"),
+    };
+
+    Ok(Arc::new(config))
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect())
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))
+}
+
+/// Certificate verifier accepting any server certificate (`--patroni-insecure`).
+/// Handshake signatures are still checked so the connection stays consistent.
+#[derive(Debug)]
+struct NoVerification {
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
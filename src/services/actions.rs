@@ -1,132 +1,531 @@
-use crate::patroni::patroni::{ClusterInfo, Patroni};
-use anyhow::{Result, anyhow};
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, NodeStatus, Patroni};
+use crate::services::audit::{AuditEntry, AuditLog};
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
 
+#[derive(Clone)]
 pub struct ActionsService {
     pub patroni_client: Patroni,
+    audit_log: Option<AuditLog>,
+    /// Why requests of the current action could not be fully recorded in the
+    /// audit log; collected from every request, e.g. of a reload of all members
+    audit_warnings: Arc<Mutex<Vec<String>>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Action {
     Switchover,
+    Failover,
     Restart,
+    RollingRestart,
+    Reload,
     Reinitialize,
     PauseCluster,
     ResumeCluster,
+    CancelScheduledSwitchover,
+    CancelScheduledRestart,
+    EnableSyncMode,
+    DisableSyncMode,
+    SetSyncNodeCount,
 }
 
 impl Action {
     pub fn as_str(&self) -> &'static str {
         match self {
             Action::Switchover => "Switchover",
+            Action::Failover => "Failover",
             Action::Restart => "Restart Node",
+            Action::RollingRestart => "Rolling Restart",
+            Action::Reload => "Reload",
             Action::Reinitialize => "Reinitialize Node",
             Action::PauseCluster => "Pause Cluster",
             Action::ResumeCluster => "Resume Cluster",
+            Action::CancelScheduledSwitchover => "Cancel Switchover",
+            Action::CancelScheduledRestart => "Cancel Restart",
+            Action::EnableSyncMode => "Enable Sync Mode",
+            Action::DisableSyncMode => "Disable Sync Mode",
+            Action::SetSyncNodeCount => "Set Sync Node Count",
         }
     }
 
     pub fn description(&self) -> &'static str {
         match self {
             Action::Switchover => "Change the leader node in the cluster",
+            Action::Failover => "Promote a replica when there is no healthy leader",
             Action::Restart => "Restart a specific node in the cluster",
+            Action::RollingRestart => {
+                "Restart every member in turn: replicas first, then switch over and restart the old leader"
+            }
+            Action::Reload => "Reload the Patroni and PostgreSQL configuration of one or all nodes",
             Action::Reinitialize => "Reinitialize a specific node in the cluster",
             Action::PauseCluster => "Pause automatic failover in the cluster",
             Action::ResumeCluster => "Resume automatic failover in the cluster",
+            Action::CancelScheduledSwitchover => "Cancel the pending scheduled switchover",
+            Action::CancelScheduledRestart => "Cancel a pending scheduled restart of a node",
+            Action::EnableSyncMode => "Make commits wait until synchronous standbys have received them",
+            Action::DisableSyncMode => "Stop waiting for synchronous standbys; a failover may then lose commits",
+            Action::SetSyncNodeCount => "Change how many synchronous standbys the leader waits for",
         }
     }
 
     pub fn is_destructive(&self) -> bool {
         match self {
             Action::Switchover => true,
+            Action::Failover => true,
             Action::Restart => true,
+            Action::RollingRestart => true,
+            Action::Reload => false,
             Action::Reinitialize => true,
             Action::PauseCluster => true,
             Action::ResumeCluster => false,
+            // Confirmed so a queued maintenance window is not dropped by accident
+            Action::CancelScheduledSwitchover => true,
+            Action::CancelScheduledRestart => true,
+            Action::EnableSyncMode => true,
+            Action::DisableSyncMode => true,
+            Action::SetSyncNodeCount => true,
         }
     }
 
+    /// Whether pre-flight checks must pass before the action is sent
+    pub fn has_preflight(&self) -> bool {
+        matches!(self, Action::Switchover | Action::Reinitialize)
+    }
+
+    /// Members the action can be aimed at, or `None` for cluster-wide actions
+    pub fn target_candidates<'a>(&self, cluster: &'a ClusterInfo) -> Option<Vec<&'a NodeStatus>> {
+        match self {
+            // Only replicas can become the new leader
+            Action::Switchover => Some(cluster.members.iter().filter(|n| n.role != "leader").collect()),
+            Action::Failover => Some(cluster.failover_candidates()),
+            Action::Restart | Action::Reload | Action::Reinitialize => Some(cluster.members.iter().collect()),
+            Action::CancelScheduledRestart => Some(
+                cluster
+                    .members
+                    .iter()
+                    .filter(|n| n.scheduled_restart.is_some())
+                    .collect(),
+            ),
+            Action::RollingRestart
+            | Action::PauseCluster
+            | Action::ResumeCluster
+            | Action::CancelScheduledSwitchover
+            | Action::EnableSyncMode
+            | Action::DisableSyncMode
+            | Action::SetSyncNodeCount => None,
+        }
+    }
+
+    /// Whether the action can only be checked against a known cluster state
+    pub fn needs_cluster_state(&self) -> bool {
+        matches!(
+            self,
+            Action::Failover
+                | Action::RollingRestart
+                | Action::CancelScheduledSwitchover
+                | Action::CancelScheduledRestart
+        )
+    }
+
+    /// Why the action cannot run in the given cluster state, if it cannot
+    pub fn blocked_reason(&self, cluster: &ClusterInfo, max_lag_us: u64) -> Option<String> {
+        match self {
+            Action::Switchover if cluster.leader_node_name.is_none() => {
+                Some("The cluster has no leader, use Failover".to_string())
+            }
+            Action::Failover if cluster.has_healthy_leader() => Some(format!(
+                "Leader {} is healthy, use Switchover",
+                cluster.leader_node_name.as_deref().unwrap_or_default()
+            )),
+            Action::Failover if cluster.failover_candidates().is_empty() => {
+                Some("No member is eligible for failover".to_string())
+            }
+            Action::RollingRestart if !cluster.has_healthy_leader() => {
+                Some("Rolling restart needs a healthy leader".to_string())
+            }
+            Action::RollingRestart if cluster.failover_candidates().is_empty() => {
+                Some("Rolling restart needs a replica to switch over to".to_string())
+            }
+            Action::RollingRestart if !cluster.replication_ok(max_lag_us) => {
+                Some("Replication is not healthy, fix it before a rolling restart".to_string())
+            }
+            Action::CancelScheduledSwitchover if cluster.scheduled_switchover.is_none() => {
+                Some("No switchover is scheduled".to_string())
+            }
+            Action::CancelScheduledRestart
+                if !cluster.members.iter().any(|n| n.scheduled_restart.is_some()) =>
+            {
+                Some("No restart is scheduled".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the action can be queued for a later time
+    pub fn can_schedule(&self) -> bool {
+        matches!(self, Action::Switchover | Action::Restart)
+    }
+
     pub fn all() -> Vec<Action> {
         vec![
             Action::Switchover,
+            Action::Failover,
             Action::Restart,
+            Action::RollingRestart,
+            Action::Reload,
             Action::Reinitialize,
             Action::PauseCluster,
             Action::ResumeCluster,
+            Action::CancelScheduledSwitchover,
+            Action::CancelScheduledRestart,
+            Action::EnableSyncMode,
+            Action::DisableSyncMode,
+            Action::SetSyncNodeCount,
         ]
     }
 }
 
+/// Conditions and options of `POST /restart`
+#[derive(Debug, Clone, Default)]
+pub struct RestartOptions {
+    /// When to restart (ISO 8601 with time zone); right away if unset
+    pub schedule: Option<String>,
+    /// Only restart if the member has a restart pending
+    pub restart_pending: bool,
+    /// Only restart if the member has this role (`primary` or `replica`)
+    pub role: Option<String>,
+    /// Only restart if the running PostgreSQL is older than this version
+    pub postgres_version: Option<String>,
+    /// Seconds to wait for PostgreSQL to come up before failing
+    pub timeout: Option<u64>,
+}
+
+impl RestartOptions {
+    fn to_json(&self) -> Value {
+        let mut body = json!({});
+        if let Some(schedule) = &self.schedule {
+            body["schedule"] = json!(schedule);
+        }
+        if self.restart_pending {
+            body["restart_pending"] = json!(true);
+        }
+        if let Some(role) = &self.role {
+            body["role"] = json!(role);
+        }
+        if let Some(version) = &self.postgres_version {
+            body["postgres_version"] = json!(version);
+        }
+        if let Some(timeout) = self.timeout {
+            body["timeout"] = json!(timeout);
+        }
+
+        body
+    }
+
+    /// Human readable list of the conditions and options that are set
+    pub fn summary(&self) -> Vec<String> {
+        let mut summary = vec![];
+        if self.restart_pending {
+            summary.push("only if a restart is pending".to_string());
+        }
+        if let Some(role) = &self.role {
+            summary.push(format!("only if role is {role}"));
+        }
+        if let Some(version) = &self.postgres_version {
+            summary.push(format!("only if version < {version}"));
+        }
+        if let Some(timeout) = self.timeout {
+            summary.push(format!("timeout {timeout}s"));
+        }
+
+        summary
+    }
+}
+
 impl ActionsService {
-    pub fn new(patroni_client: Patroni) -> Self {
-        ActionsService { patroni_client }
+    /// Create the service; with an audit log every request is recorded in it
+    pub fn new(patroni_client: Patroni, audit_log: Option<AuditLog>) -> Self {
+        ActionsService {
+            patroni_client,
+            audit_log,
+            audit_warnings: Arc::new(Mutex::new(vec![])),
+        }
     }
 
-    pub fn get_cluster_info(&self) -> ClusterInfo {
-        self.patroni_client.get_cluster_info()
+    /// Why requests of the current action could not be fully recorded in the audit log
+    pub fn audit_warnings(&self) -> Vec<String> {
+        self.audit_warnings.lock().unwrap_or_else(|e| e.into_inner()).clone()
     }
 
-    pub fn switchover(&self, leader: &str, candidate: &str) -> Result<()> {
-        let url = format!("{}/switchover", self.patroni_client.base_url());
-        let body = format!(r#"{{"leader": "{}", "candidate": "{}"}}"#, leader, candidate);
+    /// Forget the warnings of the previous action; called when a new one starts
+    pub fn clear_audit_warnings(&self) {
+        self.audit_warnings.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
 
-        match ureq::post(&url)
-            .set("Content-Type", "application/json")
-            .send_string(&body)
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("Failed to perform switchover: {}", e)),
+    /// Switch the leader to `candidate`, at `scheduled_at` (ISO 8601 with
+    /// time zone) if given, otherwise right away
+    pub fn switchover(&self, leader: &str, candidate: &str, scheduled_at: Option<&str>) -> Result<()> {
+        let mut body = json!({"leader": leader, "candidate": candidate});
+        if let Some(at) = scheduled_at {
+            body["scheduled_at"] = json!(at);
         }
+
+        self.send(
+            ActionRequest::cluster("switchover", "POST", "/switchover", Some(body)).target(candidate),
+            "Failed to perform switchover".to_string(),
+        )
     }
 
-    pub fn restart_node(&self, node: &str) -> Result<()> {
-        let url = format!("{}/restart", self.patroni_client.base_url());
-        let body = format!(r#"{{"restart": true}}"#);
+    /// Promote `candidate` without the consent of a leader. Unlike a
+    /// switchover this also works when the leader is gone.
+    pub fn failover(&self, candidate: &str) -> Result<()> {
+        let body = json!({"candidate": candidate});
 
-        match ureq::post(&url)
-            .set("Content-Type", "application/json")
-            .send_string(&body)
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("Failed to restart node {}: {}", node, e)),
+        self.send(
+            ActionRequest::cluster("failover", "POST", "/failover", Some(body)).target(candidate),
+            format!("Failed to fail over to {}", candidate),
+        )
+    }
+
+    /// Restart PostgreSQL on `node` through its own REST API
+    pub fn restart_node(&self, node: &NodeStatus, options: &RestartOptions) -> Result<()> {
+        self.send(
+            ActionRequest::node("restart", node, "POST", "/restart", Some(options.to_json())),
+            format!("Failed to restart node {}", node.name),
+        )
+    }
+
+    /// Cancel the pending scheduled switchover
+    pub fn cancel_scheduled_switchover(&self) -> Result<()> {
+        self.send(
+            ActionRequest::cluster("cancel_switchover", "DELETE", "/switchover", None),
+            "Failed to cancel scheduled switchover".to_string(),
+        )
+    }
+
+    /// Cancel the scheduled restart of `node`, which only that member knows about
+    pub fn cancel_scheduled_restart(&self, node: &NodeStatus) -> Result<()> {
+        self.send(
+            ActionRequest::node("cancel_restart", node, "DELETE", "/restart", None),
+            format!("Failed to cancel scheduled restart of {}", node.name),
+        )
+    }
+
+    /// Make `node` re-read patroni.yml and reload PostgreSQL
+    pub fn reload_node(&self, node: &NodeStatus) -> Result<()> {
+        self.send(
+            ActionRequest::node("reload", node, "POST", "/reload", None),
+            format!("Failed to reload node {}", node.name),
+        )
+    }
+
+    /// Rebuild the data directory of `node` from the leader. With `force` the
+    /// member is reinitialized even while PostgreSQL is running.
+    pub fn reinitialize_node(&self, node: &NodeStatus, force: bool) -> Result<()> {
+        self.send(
+            ActionRequest::node("reinitialize", node, "POST", "/reinitialize", Some(json!({"force": force}))),
+            format!("Failed to reinitialize node {}", node.name),
+        )
+    }
+
+    pub fn pause_cluster(&self) -> Result<()> {
+        self.send(
+            ActionRequest::cluster("pause", "PATCH", "/config", Some(json!({"pause": true}))),
+            "Failed to pause cluster".to_string(),
+        )
+    }
+
+    pub fn resume_cluster(&self) -> Result<()> {
+        self.send(
+            ActionRequest::cluster("resume", "PATCH", "/config", Some(json!({"pause": false}))),
+            "Failed to resume cluster".to_string(),
+        )
+    }
+
+    /// Turn synchronous replication on or off. `strict` makes commits wait
+    /// even when no synchronous standby is available; it is left as is if unset.
+    pub fn set_synchronous_mode(&self, enabled: bool, strict: Option<bool>) -> Result<()> {
+        let mut body = json!({"synchronous_mode": enabled});
+        if let Some(strict) = strict {
+            body["synchronous_mode_strict"] = json!(strict);
         }
+
+        self.send(
+            ActionRequest::cluster(
+                if enabled { "enable_sync_mode" } else { "disable_sync_mode" },
+                "PATCH",
+                "/config",
+                Some(body),
+            ),
+            format!("Failed to {} synchronous mode", if enabled { "enable" } else { "disable" }),
+        )
+    }
+
+    /// Set how many synchronous standbys the leader waits for
+    pub fn set_synchronous_node_count(&self, count: u64) -> Result<()> {
+        self.send(
+            ActionRequest::cluster(
+                "sync_node_count",
+                "PATCH",
+                "/config",
+                Some(json!({"synchronous_node_count": count})),
+            ),
+            format!("Failed to set synchronous_node_count to {count}"),
+        )
+    }
+
+    /// Merge `patch` into the dynamic configuration. Keys set to `null` are removed.
+    pub fn patch_config(&self, patch: &Value) -> Result<()> {
+        self.send(
+            ActionRequest::cluster("config", "PATCH", "/config", Some(patch.clone())),
+            "Failed to update the dynamic configuration".to_string(),
+        )
     }
 
-    pub fn reinitialize_node(&self, node: &str) -> Result<()> {
-        let url = format!("{}/reinitialize", self.patroni_client.base_url());
-        let body = format!(r#"{{"reinitialize": true}}"#);
+    /// Send `request` and record it in the audit log with the cluster state
+    /// before and after. An action that cannot be recorded is still sent, the
+    /// reason is added to `audit_warnings`.
+    fn send(&self, request: ActionRequest, attempt: String) -> Result<()> {
+        let audit = self.audit_log.as_ref().and_then(|audit_log| match audit_log.open() {
+            Ok(file) => Some((audit_log, file)),
+            Err(e) => {
+                self.add_audit_warning(format!("{e:#}"));
+                None
+            }
+        });
+        let Some((audit_log, file)) = audit else {
+            return self.send_request(&request).map(|_| ()).map_err(|e| failed(e, attempt));
+        };
 
-        match ureq::post(&url)
-            .set("Content-Type", "application/json")
-            .send_string(&body)
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("Failed to reinitialize node {}: {}", node, e)),
+        let before = self.patroni_client.get_cluster_info().into();
+        let result = self.send_request(&request);
+        // The action was sent either way, a failed fetch only leaves the state out
+        let (after, after_warning) = match self.patroni_client.get_cluster_info() {
+            Ok(cluster) => (Some(Ok(cluster).into()), None),
+            Err(e) => (None, Some(format!("Cannot fetch the cluster state after {}: {e}", request.action))),
+        };
+
+        let (url, status, error) = match &result {
+            Ok(resp) => (resp.get_url().to_string(), Some(resp.status()), None),
+            Err(e) => (e.url().to_string(), e.status(), Some(e.to_string())),
+        };
+        let body = request.body.as_ref().map(Value::to_string);
+        let recorded = audit_log.record(
+            file,
+            AuditEntry {
+                action: request.action,
+                target: request.target.as_deref(),
+                method: request.method,
+                url,
+                body: body.as_deref(),
+                status,
+                error,
+                before,
+                after,
+            },
+        );
+        if let Err(e) = recorded {
+            self.add_audit_warning(format!("{e:#}"));
+        }
+        if let Some(warning) = after_warning {
+            self.add_audit_warning(warning);
         }
+
+        result.map(|_| ()).map_err(|e| failed(e, attempt))
     }
 
-    pub fn pause_cluster(&self) -> Result<()> {
-        let url = format!("{}/pause", self.patroni_client.base_url());
-        let body = r#"{"paused": true}"#;
+    fn add_audit_warning(&self, warning: String) {
+        let mut warnings = self.audit_warnings.lock().unwrap_or_else(|e| e.into_inner());
+        // Every member of a reload fails the same way when the log cannot be opened
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
 
-        match ureq::patch(&url)
-            .set("Content-Type", "application/json")
-            .send_string(body)
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("Failed to pause cluster: {}", e)),
+    fn send_request(&self, request: &ActionRequest) -> Result<ureq::Response, PatroniError> {
+        let body = request.body.as_ref().map(Value::to_string);
+        match request.node {
+            Some(node) => self.send_to_node(node, request.method, request.path, body.as_deref()),
+            None => self
+                .patroni_client
+                .send(request.method, request.path, body.as_deref())
+                .map_err(PatroniError::from),
         }
     }
 
-    pub fn resume_cluster(&self) -> Result<()> {
-        let url = format!("{}/pause", self.patroni_client.base_url());
-        let body = r#"{"paused": false}"#;
+    /// Send a per-node request to the member's own `api_url`
+    fn send_to_node(
+        &self,
+        node: &NodeStatus,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<ureq::Response, PatroniError> {
+        let Some(api_url) = &node.api_url else {
+            return Err(PatroniError::schema(
+                "/cluster",
+                format!("member {} has no api_url", node.name),
+            ));
+        };
+
+        self.patroni_client
+            .send_to_member(api_url, method, path, body)
+            .map_err(PatroniError::from)
+    }
+}
+
+/// A request changing the cluster, as sent and audited by `ActionsService`
+struct ActionRequest<'a> {
+    /// Name of the action in the audit log
+    action: &'static str,
+    /// Member the action is about
+    target: Option<String>,
+    /// Member whose own endpoint receives the request; any member if unset
+    node: Option<&'a NodeStatus>,
+    method: &'static str,
+    path: &'static str,
+    body: Option<Value>,
+}
 
-        match ureq::patch(&url)
-            .set("Content-Type", "application/json")
-            .send_string(body)
-        {
-            Ok(_) => Ok(()),
-            Err(e) => Err(anyhow!("Failed to resume cluster: {}", e)),
+impl<'a> ActionRequest<'a> {
+    fn cluster(action: &'static str, method: &'static str, path: &'static str, body: Option<Value>) -> Self {
+        ActionRequest {
+            action,
+            target: None,
+            node: None,
+            method,
+            path,
+            body,
         }
     }
-}
\ No newline at end of file
+
+    fn node(
+        action: &'static str,
+        node: &'a NodeStatus,
+        method: &'static str,
+        path: &'static str,
+        body: Option<Value>,
+    ) -> Self {
+        ActionRequest {
+            target: Some(node.name.clone()),
+            node: Some(node),
+            ..Self::cluster(action, method, path, body)
+        }
+    }
+
+    fn target(self, target: &str) -> Self {
+        ActionRequest {
+            target: Some(target.to_string()),
+            ..self
+        }
+    }
+}
+
+/// Wrap a Patroni error with what was being attempted. The `PatroniError`
+/// stays in the chain so callers can tell unreachable members from rejected
+/// requests; print with `{:#}` to include it.
+fn failed(err: impl Into<PatroniError>, attempt: String) -> anyhow::Error {
+    anyhow::Error::new(err.into()).context(attempt)
+}
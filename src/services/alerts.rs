@@ -0,0 +1,548 @@
+use crate::config::Config;
+use crate::services::collector::Snapshot;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a command or webhook may take before the alert counts as failed
+const SINK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A change between two successive snapshots worth telling someone about
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertEvent {
+    /// `leader_changed`, `member_disappeared`, `replica_lagging`,
+    /// `service_down`, `vip_moved` or `cluster_paused`
+    pub event: &'static str,
+    /// Name of the watched cluster (its profile)
+    pub cluster: String,
+    pub scope: Option<String>,
+    /// Member, service or VIP the event is about
+    pub subject: String,
+    pub previous: Option<String>,
+    pub current: Option<String>,
+    pub message: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+/// Where alerts are delivered
+#[derive(Debug, Clone)]
+pub enum AlertSink {
+    /// Shell command run with the event as JSON on stdin and in `CLUP_*` variables
+    Command(String),
+    /// URL the event is POSTed to as JSON
+    Webhook(String),
+    /// File the event is appended to as a JSON line
+    File(PathBuf),
+}
+
+impl AlertSink {
+    fn send(&self, event: &AlertEvent) -> Result<()> {
+        let json = serde_json::to_string(event)?;
+        match self {
+            AlertSink::Command(command) => run_command(command, event, &json),
+            AlertSink::Webhook(url) => {
+                ureq::AgentBuilder::new()
+                    .timeout(SINK_TIMEOUT)
+                    .build()
+                    .post(url)
+                    .set("Content-Type", "application/json")
+                    .send_string(&json)
+                    .map_err(|e| anyhow!("webhook {url}: {e}"))?;
+                Ok(())
+            }
+            AlertSink::File(path) => {
+                let mut file = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("cannot open {}", path.display()))?;
+                writeln!(file, "{json}").with_context(|| format!("cannot write {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Run `command` through the shell, killing it if it does not finish in time.
+/// Its output is discarded so it cannot garble the TUI.
+fn run_command(command: &str, event: &AlertEvent, json: &str) -> Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .env("CLUP_EVENT", event.event)
+        .env("CLUP_CLUSTER", &event.cluster)
+        .env("CLUP_SUBJECT", &event.subject)
+        .env("CLUP_MESSAGE", &event.message)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("cannot run {command}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Commands that ignore the payload may exit before reading it
+        let _ = writeln!(stdin, "{json}");
+    }
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                bail!("{command} failed with {status}");
+            }
+            return Ok(());
+        }
+        if started.elapsed() > SINK_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("{command} timed out after {}s", SINK_TIMEOUT.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// The parts of a snapshot alerts are raised on. `None` means the source
+/// could not be fetched, in which case nothing is compared.
+#[derive(Debug, Clone, Default)]
+struct Observed {
+    cluster: Option<ClusterObserved>,
+    host: Option<HostObserved>,
+}
+
+#[derive(Debug, Clone)]
+struct ClusterObserved {
+    scope: String,
+    /// `None` while the cluster has no leader
+    leader: Option<String>,
+    members: BTreeSet<String>,
+    /// Replicas with a known lag above the limit, with that lag
+    lagging: BTreeMap<String, u64>,
+    paused: bool,
+}
+
+#[derive(Debug, Clone)]
+struct HostObserved {
+    hostname: String,
+    down_services: BTreeSet<String>,
+    vip: String,
+}
+
+impl Observed {
+    fn from_snapshot(snapshot: &Snapshot, max_lag_us: u64) -> Self {
+        let cluster = snapshot
+            .cluster
+            .as_ref()
+            .filter(|_| snapshot.cluster_error.is_none())
+            .map(|s| {
+                let c = &s.value;
+                ClusterObserved {
+                    scope: c.scope.clone(),
+                    leader: c.leader().map(|n| n.name.clone()),
+                    members: c.members.iter().map(|n| n.name.clone()).collect(),
+                    lagging: c
+                        .members
+                        .iter()
+                        .filter(|n| n.role != "leader")
+                        .filter_map(|n| n.lag.filter(|lag| *lag > max_lag_us).map(|lag| (n.name.clone(), lag)))
+                        .collect(),
+                    paused: c.paused,
+                }
+            });
+        let host = snapshot.overview.as_ref().map(|s| HostObserved {
+            hostname: s.value.hostname.clone(),
+            down_services: s.value.components.iter().filter(|c| !c.up).map(|c| c.name.clone()).collect(),
+            vip: s.value.vip.clone(),
+        });
+
+        Observed { cluster, host }
+    }
+}
+
+/// Compares successive snapshots of one cluster and sends the transitions
+/// to the configured sinks
+pub struct AlertService {
+    cluster: String,
+    sinks: Vec<AlertSink>,
+    max_lag_us: u64,
+    /// Last known state of each source; the first snapshot only sets it
+    previous: Mutex<Observed>,
+}
+
+impl AlertService {
+    pub fn new(config: &Config, cluster: &str) -> Self {
+        AlertService {
+            cluster: cluster.to_string(),
+            sinks: config.alert_sinks(),
+            max_lag_us: config.max_replication_lag_us(),
+            previous: Mutex::new(Observed::default()),
+        }
+    }
+
+    pub fn has_sinks(&self) -> bool {
+        !self.sinks.is_empty()
+    }
+
+    /// Detect the transitions since the previous call and send them to every
+    /// sink. Returns the events together with the sink failures.
+    pub fn check(&self, snapshot: &Snapshot) -> (Vec<AlertEvent>, Vec<String>) {
+        let current = Observed::from_snapshot(snapshot, self.max_lag_us);
+        let events = {
+            let mut previous = self.previous.lock().unwrap_or_else(|e| e.into_inner());
+            let events = self.transitions(&previous, &current);
+            // A source that could not be fetched keeps its last known state
+            if current.cluster.is_some() {
+                previous.cluster = current.cluster;
+            }
+            if current.host.is_some() {
+                previous.host = current.host;
+            }
+            events
+        };
+
+        let mut failures = vec![];
+        for event in &events {
+            for sink in &self.sinks {
+                if let Err(e) = sink.send(event) {
+                    failures.push(format!("{e:#}"));
+                }
+            }
+        }
+
+        (events, failures)
+    }
+
+    fn transitions(&self, previous: &Observed, current: &Observed) -> Vec<AlertEvent> {
+        let mut events = vec![];
+
+        if let (Some(before), Some(now)) = (&previous.cluster, &current.cluster) {
+            let scope = Some(now.scope.clone());
+            if before.leader != now.leader {
+                events.push(self.event(
+                    "leader_changed",
+                    scope.clone(),
+                    &now.scope,
+                    before.leader.as_deref(),
+                    now.leader.as_deref(),
+                    match (before.leader.as_deref(), now.leader.as_deref()) {
+                        (Some(from), None) => format!("cluster {} lost its leader {from}", now.scope),
+                        (None, Some(to)) => format!("{to} is now the leader of {}", now.scope),
+                        (Some(from), Some(to)) => format!("leader of {} changed from {from} to {to}", now.scope),
+                        (None, None) => unreachable!("the leaders differ"),
+                    },
+                ));
+            }
+
+            for member in before.members.difference(&now.members) {
+                events.push(self.event(
+                    "member_disappeared",
+                    scope.clone(),
+                    member,
+                    None,
+                    None,
+                    format!("{member} is no longer a member of {}", now.scope),
+                ));
+            }
+
+            for (member, lag) in &now.lagging {
+                if !before.lagging.contains_key(member) {
+                    events.push(self.event(
+                        "replica_lagging",
+                        scope.clone(),
+                        member,
+                        None,
+                        Some(seconds(*lag).as_str()),
+                        format!("{member} lags {}, above the limit of {}", seconds(*lag), seconds(self.max_lag_us)),
+                    ));
+                }
+            }
+
+            if !before.paused && now.paused {
+                events.push(self.event(
+                    "cluster_paused",
+                    scope.clone(),
+                    &now.scope,
+                    None,
+                    None,
+                    format!("automatic failover of {} is paused", now.scope),
+                ));
+            }
+        }
+
+        if let (Some(before), Some(now)) = (&previous.host, &current.host) {
+            for service in now.down_services.difference(&before.down_services) {
+                events.push(self.event(
+                    "service_down",
+                    None,
+                    service,
+                    Some("UP"),
+                    Some("DOWN"),
+                    format!("{service} went down on {}", now.hostname),
+                ));
+            }
+
+            // The VIP shows as `-` on hosts that do not hold it
+            if before.vip != now.vip {
+                let (subject, message) = if now.vip == "-" {
+                    (&before.vip, format!("VIP {} moved away from {}", before.vip, now.hostname))
+                } else {
+                    (&now.vip, format!("VIP {} moved to {}", now.vip, now.hostname))
+                };
+                events.push(self.event(
+                    "vip_moved",
+                    None,
+                    subject,
+                    Some(&before.vip),
+                    Some(&now.vip),
+                    message,
+                ));
+            }
+        }
+
+        events
+    }
+
+    fn event(
+        &self,
+        event: &'static str,
+        scope: Option<String>,
+        subject: &str,
+        previous: Option<&str>,
+        current: Option<&str>,
+        message: String,
+    ) -> AlertEvent {
+        AlertEvent {
+            event,
+            cluster: self.cluster.clone(),
+            scope,
+            subject: subject.to_string(),
+            previous: previous.map(str::to_string),
+            current: current.map(str::to_string),
+            message,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+/// Lag in microseconds as the dashboard shows it
+fn seconds(micros: u64) -> String {
+    format!("{:.1}s", micros as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    fn service() -> AlertService {
+        AlertService {
+            cluster: "prod".to_string(),
+            sinks: vec![],
+            max_lag_us: 10_000_000,
+            previous: Mutex::new(Observed::default()),
+        }
+    }
+
+    fn observed(leader: Option<&str>) -> Observed {
+        Observed {
+            cluster: Some(ClusterObserved {
+                scope: "demo".to_string(),
+                leader: leader.map(str::to_string),
+                members: ["pg1", "pg2"].map(str::to_string).into(),
+                lagging: BTreeMap::new(),
+                paused: false,
+            }),
+            host: None,
+        }
+    }
+
+    #[test]
+    fn leader_lost() {
+        let events = service().transitions(&observed(Some("pg1")), &observed(None));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "leader_changed");
+        assert_eq!(events[0].previous.as_deref(), Some("pg1"));
+        assert_eq!(events[0].current, None);
+        assert_eq!(events[0].message, "cluster demo lost its leader pg1");
+    }
+
+    #[test]
+    fn leader_gained() {
+        let events = service().transitions(&observed(None), &observed(Some("pg2")));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].previous, None);
+        assert_eq!(events[0].current.as_deref(), Some("pg2"));
+        assert_eq!(events[0].message, "pg2 is now the leader of demo");
+    }
+
+    #[test]
+    fn leader_changed() {
+        let events = service().transitions(&observed(Some("pg1")), &observed(Some("pg2")));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].subject, "demo");
+        assert_eq!(events[0].previous.as_deref(), Some("pg1"));
+        assert_eq!(events[0].current.as_deref(), Some("pg2"));
+        assert_eq!(events[0].message, "leader of demo changed from pg1 to pg2");
+    }
+
+    #[test]
+    fn same_leader_raises_nothing() {
+        assert!(service().transitions(&observed(Some("pg1")), &observed(Some("pg1"))).is_empty());
+        assert!(service().transitions(&observed(None), &observed(None)).is_empty());
+    }
+
+    fn host(down_services: &[&str], vip: &str) -> Observed {
+        Observed {
+            cluster: None,
+            host: Some(HostObserved {
+                hostname: "db1".to_string(),
+                down_services: down_services.iter().map(|s| s.to_string()).collect(),
+                vip: vip.to_string(),
+            }),
+        }
+    }
+
+    /// Edit the cluster part of `observed`
+    fn with_cluster(mut observed: Observed, edit: impl FnOnce(&mut ClusterObserved)) -> Observed {
+        edit(observed.cluster.as_mut().unwrap());
+        observed
+    }
+
+    #[test]
+    fn member_disappeared() {
+        let now = with_cluster(observed(Some("pg1")), |c| {
+            c.members.remove("pg2");
+        });
+        let events = service().transitions(&observed(Some("pg1")), &now);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "member_disappeared");
+        assert_eq!(events[0].subject, "pg2");
+        assert_eq!(events[0].message, "pg2 is no longer a member of demo");
+    }
+
+    #[test]
+    fn replica_lagging_above_the_limit() {
+        let lagging = with_cluster(observed(Some("pg1")), |c| {
+            c.lagging.insert("pg2".to_string(), 12_500_000);
+        });
+        let events = service().transitions(&observed(Some("pg1")), &lagging);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "replica_lagging");
+        assert_eq!(events[0].subject, "pg2");
+        assert_eq!(events[0].current.as_deref(), Some("12.5s"));
+        assert_eq!(events[0].message, "pg2 lags 12.5s, above the limit of 10.0s");
+
+        // Still lagging is not a new transition
+        assert!(service().transitions(&lagging, &lagging).is_empty());
+    }
+
+    #[test]
+    fn cluster_paused() {
+        let paused = with_cluster(observed(Some("pg1")), |c| c.paused = true);
+
+        let events = service().transitions(&observed(Some("pg1")), &paused);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "cluster_paused");
+        assert_eq!(events[0].message, "automatic failover of demo is paused");
+
+        // Resuming is not alerted
+        assert!(service().transitions(&paused, &observed(Some("pg1"))).is_empty());
+    }
+
+    #[test]
+    fn service_down() {
+        let events = service().transitions(&host(&[], "-"), &host(&["haproxy"], "-"));
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "service_down");
+        assert_eq!(events[0].scope, None);
+        assert_eq!(events[0].subject, "haproxy");
+        assert_eq!(events[0].message, "haproxy went down on db1");
+    }
+
+    #[test]
+    fn vip_moved() {
+        let events = service().transitions(&host(&[], "-"), &host(&[], "10.0.0.100"));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "vip_moved");
+        assert_eq!(events[0].subject, "10.0.0.100");
+        assert_eq!(events[0].message, "VIP 10.0.0.100 moved to db1");
+
+        let events = service().transitions(&host(&[], "10.0.0.100"), &host(&[], "-"));
+        assert_eq!(events[0].subject, "10.0.0.100");
+        assert_eq!(events[0].message, "VIP 10.0.0.100 moved away from db1");
+    }
+
+    #[test]
+    fn file_sink_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("clup-alerts-{}.jsonl", std::process::id()));
+        let sink = AlertSink::File(path.clone());
+
+        let events = service().transitions(&host(&[], "-"), &host(&["haproxy", "pgbouncer"], "-"));
+        for event in &events {
+            sink.send(event).unwrap();
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let subjects: Vec<String> = text
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["subject"].to_string())
+            .collect();
+        assert_eq!(subjects, ["\"haproxy\"", "\"pgbouncer\""]);
+    }
+
+    #[test]
+    fn webhook_posts_the_event() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        // Answer a single request, handing back its request line and body
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = stream;
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+            (request_line, String::from_utf8(body).unwrap())
+        });
+
+        let event = service().transitions(&observed(Some("pg1")), &observed(Some("pg2"))).remove(0);
+        AlertSink::Webhook(url).send(&event).unwrap();
+
+        let (request_line, body) = server.join().unwrap();
+        assert!(request_line.starts_with("POST /hook "));
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["event"], "leader_changed");
+        assert_eq!(payload["cluster"], "prod");
+        assert_eq!(payload["previous"], "pg1");
+        assert_eq!(payload["current"], "pg2");
+    }
+}
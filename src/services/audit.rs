@@ -0,0 +1,304 @@
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::ClusterInfo;
+use crate::system;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// JSON-lines file recording every action sent to Patroni
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    /// Name of the cluster (profile) the actions are sent to
+    cluster: String,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// UTC time the request was sent, ISO 8601
+    pub time: String,
+    pub user: String,
+    /// User who ran clup through sudo, if any
+    #[serde(default)]
+    pub sudo_user: Option<String>,
+    pub hostname: String,
+    pub cluster: String,
+    pub action: String,
+    /// Member the action is about
+    pub target: Option<String>,
+    pub method: String,
+    /// URL the request was sent to
+    pub url: String,
+    /// Request body; not JSON-decoded bodies are kept as a string
+    pub request: Option<Value>,
+    /// HTTP status of the response, `None` if no response was received
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    /// Cluster state the action was sent in
+    pub before: AuditClusterState,
+    /// Cluster state once Patroni answered, `None` if it could not be fetched
+    #[serde(default)]
+    pub after: Option<AuditClusterState>,
+}
+
+/// Cluster state recorded around an action
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditClusterState {
+    /// Empty when the cluster has no leader
+    #[serde(default)]
+    pub leader: String,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub members: Vec<AuditMember>,
+    /// Why the state could not be fetched
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditMember {
+    pub name: String,
+    pub role: String,
+    pub state: String,
+    pub timeline: Option<u64>,
+    pub lag: Option<u64>,
+}
+
+impl From<Result<ClusterInfo, PatroniError>> for AuditClusterState {
+    fn from(result: Result<ClusterInfo, PatroniError>) -> Self {
+        match result {
+            Ok(cluster) => AuditClusterState {
+                leader: cluster.leader().map(|n| n.name.clone()).unwrap_or_default(),
+                paused: cluster.paused,
+                members: cluster
+                    .members
+                    .into_iter()
+                    .map(|n| AuditMember {
+                        name: n.name,
+                        role: n.role,
+                        state: n.state,
+                        timeline: n.timeline,
+                        lag: n.lag,
+                    })
+                    .collect(),
+                error: None,
+            },
+            Err(e) => AuditClusterState {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// What was sent and how it went, as passed to `AuditLog::record`
+pub struct AuditEntry<'a> {
+    pub action: &'a str,
+    pub target: Option<&'a str>,
+    pub method: &'a str,
+    pub url: String,
+    pub body: Option<&'a str>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub before: AuditClusterState,
+    pub after: Option<AuditClusterState>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, cluster: &str) -> Self {
+        AuditLog {
+            path,
+            cluster: cluster.to_string(),
+        }
+    }
+
+    /// Open the log for appending, creating it and its directory if needed.
+    /// Done before an action is sent so the failure can be reported with it.
+    pub fn open(&self) -> Result<File> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Cannot create audit log directory {}", dir.display()))?;
+        }
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Cannot open audit log {}", self.path.display()))
+    }
+
+    /// Append `entry` to the opened log as one JSON line
+    pub fn record(&self, mut file: File, entry: AuditEntry) -> Result<()> {
+        let record = AuditRecord {
+            time: utc_timestamp(SystemTime::now()),
+            user: system::current_user(),
+            sudo_user: std::env::var("SUDO_USER").ok(),
+            hostname: hostname::get().unwrap_or_default().to_string_lossy().to_string(),
+            cluster: self.cluster.clone(),
+            action: entry.action.to_string(),
+            target: entry.target.map(str::to_string),
+            method: entry.method.to_string(),
+            url: entry.url,
+            request: entry
+                .body
+                .map(|body| serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()))),
+            status: entry.status,
+            error: entry.error,
+            before: entry.before,
+            after: entry.after,
+        };
+
+        // One write per record: with O_APPEND concurrent actions cannot interleave their lines
+        let line = format!("{}\n", serde_json::to_string(&record)?);
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Cannot write audit log {}", self.path.display()))
+    }
+}
+
+/// Read the records of the audit log at `path`, oldest first. Lines that
+/// cannot be decoded are skipped; a missing file has no records.
+pub fn read_records(path: &Path) -> Result<Vec<AuditRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).with_context(|| format!("Cannot read audit log {}", path.display())),
+    };
+
+    let mut records = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Cannot read audit log {}", path.display()))?;
+        if let Ok(record) = serde_json::from_str(&line) {
+            records.push(record);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Format `time` as `YYYY-MM-DDTHH:MM:SSZ`
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn entry<'a>(action: &'a str, body: Option<&'a str>, status: Option<u16>) -> AuditEntry<'a> {
+        AuditEntry {
+            action,
+            target: Some("pg2"),
+            method: "POST",
+            url: format!("http://127.0.0.1:8008/{action}"),
+            body,
+            status,
+            error: status.is_none().then(|| "connection refused".to_string()),
+            before: AuditClusterState {
+                leader: "pg1".to_string(),
+                members: vec![AuditMember {
+                    name: "pg1".to_string(),
+                    role: "leader".to_string(),
+                    state: "running".to_string(),
+                    timeline: Some(3),
+                    lag: None,
+                }],
+                ..Default::default()
+            },
+            after: None,
+        }
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let dir = std::env::temp_dir().join(format!("clup-audit-{}", std::process::id()));
+        let path = dir.join("nested").join("audit.jsonl");
+        let log = AuditLog::new(path.clone(), "prod");
+
+        log.record(log.open().unwrap(), entry("switchover", Some(r#"{"candidate":"pg2"}"#), Some(200)))
+            .unwrap();
+        // A torn or hand-edited line is skipped, not fatal
+        log.open().unwrap().write_all(b"{\"time\": \"broken\n").unwrap();
+        let mut second = entry("restart", Some("not json"), None);
+        second.after = Some(AuditClusterState { paused: true, ..Default::default() });
+        log.record(log.open().unwrap(), second).unwrap();
+
+        let records = read_records(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(records.len(), 2);
+        let (first, second) = (&records[0], &records[1]);
+        assert_eq!(first.action, "switchover");
+        assert_eq!(first.cluster, "prod");
+        assert_eq!(first.target.as_deref(), Some("pg2"));
+        assert_eq!(first.method, "POST");
+        assert_eq!(first.url, "http://127.0.0.1:8008/switchover");
+        assert_eq!(first.request, Some(serde_json::json!({"candidate": "pg2"})));
+        assert_eq!(first.status, Some(200));
+        assert_eq!(first.error, None);
+        assert_eq!(first.before.leader, "pg1");
+        assert_eq!(first.before.members[0].timeline, Some(3));
+        assert!(first.after.is_none());
+        assert_eq!(first.time.len(), "1970-01-01T00:00:00Z".len());
+
+        assert_eq!(second.action, "restart");
+        assert_eq!(second.request, Some(Value::String("not json".to_string())));
+        assert_eq!(second.status, None);
+        assert_eq!(second.error.as_deref(), Some("connection refused"));
+        assert!(second.after.as_ref().is_some_and(|after| after.paused));
+    }
+
+    #[test]
+    fn missing_log_has_no_records() {
+        let path = std::env::temp_dir().join(format!("clup-audit-missing-{}.jsonl", std::process::id()));
+
+        assert!(read_records(&path).unwrap().is_empty());
+    }
+
+    fn at(secs: u64) -> String {
+        utc_timestamp(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn timestamp_at_the_epoch() {
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_on_leap_days() {
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_709_251_199), "2024-02-29T23:59:59Z");
+        assert_eq!(at(1_709_251_200), "2024-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn timestamp_at_a_year_boundary() {
+        assert_eq!(at(1_704_067_199), "2023-12-31T23:59:59Z");
+        assert_eq!(at(1_704_067_200), "2024-01-01T00:00:00Z");
+    }
+}
@@ -1,4 +1,6 @@
-use crate::patroni::patroni::{ClusterInfo, Patroni};
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, HistoryEntry, MemberStatus, NodeStatus, Patroni};
+use serde_json::Value;
 
 pub struct ClusterService {
     pub patroni_client: Patroni,
@@ -10,10 +12,34 @@ impl ClusterService {
     }
 
     /// Get information about the cluster
-    pub fn get_cluster_info(&self) -> ClusterInfo {
-        let mut info = self.patroni_client.get_cluster_info();
+    pub fn get_cluster_info(&self) -> Result<ClusterInfo, PatroniError> {
+        let mut info = self.patroni_client.get_cluster_info()?;
         info.members.sort_by_key(|node| node.name.clone());
 
-        info
+        Ok(info)
     }
-}
\ No newline at end of file
+
+    /// Get the failover/switchover history, newest first
+    pub fn get_history(&self) -> Result<Vec<HistoryEntry>, PatroniError> {
+        let mut history = self.patroni_client.get_history()?;
+        history.reverse();
+
+        Ok(history)
+    }
+
+    /// Get the dynamic configuration shared by all members
+    pub fn get_dynamic_config(&self) -> Result<Value, PatroniError> {
+        self.patroni_client.get_config()
+    }
+
+    /// Get the status a member reports on its own REST endpoint
+    pub fn get_member_status(&self, node: &NodeStatus) -> Result<MemberStatus, PatroniError> {
+        match &node.api_url {
+            Some(api_url) => self.patroni_client.get_member_status(api_url),
+            None => Err(PatroniError::schema(
+                "/cluster",
+                format!("member {} has no api_url", node.name),
+            )),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use crate::config::Config;
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, HistoryEntry, MemberStatus, NodeStatus};
+use crate::services::alerts::{AlertEvent, AlertService};
+use crate::services::cluster::ClusterService;
+use crate::services::logs::LogsService;
+use crate::services::overview::{OverviewData, OverviewService};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+
+/// Number of log lines kept per service
+const LOG_LINES: usize = 100;
+
+/// A value fetched by the collector together with the moment it was fetched
+#[derive(Debug, Clone)]
+pub struct Sample<T> {
+    pub value: T,
+    pub fetched_at: Instant,
+}
+
+impl<T> Sample<T> {
+    fn new(value: T) -> Self {
+        Sample {
+            value,
+            fetched_at: Instant::now(),
+        }
+    }
+
+    /// Time elapsed since the value was fetched
+    pub fn age(&self) -> Duration {
+        self.fetched_at.elapsed()
+    }
+}
+
+/// Latest state gathered by the background collector
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Last successfully fetched cluster state
+    pub cluster: Option<Sample<ClusterInfo>>,
+    /// Error of the latest cluster fetch, cleared on success
+    pub cluster_error: Option<Sample<PatroniError>>,
+    /// Timeline history, newest switch first
+    pub history: Option<Sample<Vec<HistoryEntry>>>,
+    pub history_error: Option<Sample<PatroniError>>,
+    /// Dynamic configuration from `/config`
+    pub dynamic_config: Option<Sample<Value>>,
+    pub dynamic_config_error: Option<Sample<PatroniError>>,
+    pub overview: Option<Sample<OverviewData>>,
+    pub logs: HashMap<String, Sample<Vec<String>>>,
+    /// Status of the member opened in the Cluster tab
+    pub member_detail: Option<Sample<MemberDetail>>,
+    /// Most recent alert raised on a state transition
+    pub last_alert: Option<Sample<AlertEvent>>,
+    /// Why delivering the most recent alerts failed, cleared once one succeeds
+    pub alert_error: Option<Sample<String>>,
+}
+
+/// Status fetched from a single member's own REST endpoint
+#[derive(Debug, Clone)]
+pub struct MemberDetail {
+    pub name: String,
+    pub api_url: Option<String>,
+    pub status: Result<MemberStatus, PatroniError>,
+}
+
+/// Background collector refreshing the shared snapshot on the tokio runtime.
+///
+/// Every source is polled by its own task at its own interval, so a slow
+/// Patroni endpoint never delays systemctl or journalctl data and vice versa.
+#[derive(Clone)]
+pub struct Collector {
+    snapshot: Arc<RwLock<Snapshot>>,
+    cluster_service: Arc<ClusterService>,
+    /// Member whose detail is polled, if the detail view is open
+    watched_member: Arc<Mutex<Option<NodeStatus>>>,
+}
+
+impl Collector {
+    /// Start polling only the Patroni sources of one cluster; the overview
+    /// and logs of the snapshot stay empty. Must be called from within the
+    /// tokio runtime.
+    pub fn spawn_cluster(cluster_service: ClusterService, config: &Config) -> Self {
+        let collector = Self::new(cluster_service);
+        collector.spawn_cluster_poller(config);
+
+        let target = collector.clone();
+        Self::spawn_poller(config.patroni_interval(), move || {
+            let result = target.cluster_service.get_history();
+            target.update(|s| store(&mut s.history, &mut s.history_error, result));
+        });
+
+        let target = collector.clone();
+        Self::spawn_poller(config.patroni_interval(), move || target.refresh_dynamic_config());
+
+        let target = collector.clone();
+        Self::spawn_poller(config.patroni_interval(), move || target.refresh_member());
+
+        collector
+    }
+
+    /// Start polling only what the metrics endpoint serves: `/cluster` and
+    /// the overview of the local host
+    pub fn spawn_metrics(overview_service: OverviewService, cluster_service: ClusterService, config: &Config) -> Self {
+        let collector = Self::new(cluster_service);
+        collector.spawn_cluster_poller(config);
+        collector.spawn_overview_poller(overview_service, config);
+
+        collector
+    }
+
+    /// Also poll the services, HAProxy, VIP and logs of the local host
+    pub fn spawn_host(&self, overview_service: OverviewService, logs_service: LogsService, config: &Config) {
+        self.spawn_overview_poller(overview_service, config);
+
+        let target = self.clone();
+        let services = config.services_list();
+        Self::spawn_poller(config.logs_interval(), move || {
+            for service in &services {
+                let lines = logs_service.read_logs(service, LOG_LINES);
+                target.update(|s| {
+                    s.logs.insert(service.clone(), Sample::new(lines));
+                });
+            }
+        });
+    }
+
+    /// Compare every new snapshot with the previous one and send the
+    /// transitions to the alert sinks
+    pub fn spawn_alerts(&self, alert_service: AlertService, config: &Config) {
+        let target = self.clone();
+        Self::spawn_poller(config.patroni_interval(), move || {
+            let (events, failures) = alert_service.check(&target.snapshot());
+            if events.is_empty() {
+                return;
+            }
+            target.update(|s| {
+                s.last_alert = events.last().cloned().map(Sample::new);
+                s.alert_error = failures.last().cloned().map(Sample::new);
+            });
+        });
+    }
+
+    fn new(cluster_service: ClusterService) -> Self {
+        Collector {
+            snapshot: Arc::new(RwLock::new(Snapshot::default())),
+            cluster_service: Arc::new(cluster_service),
+            watched_member: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn spawn_cluster_poller(&self, config: &Config) {
+        let target = self.clone();
+        Self::spawn_poller(config.patroni_interval(), move || {
+            let result = target.cluster_service.get_cluster_info();
+            target.update(|s| store(&mut s.cluster, &mut s.cluster_error, result));
+        });
+    }
+
+    fn spawn_overview_poller(&self, overview_service: OverviewService, config: &Config) {
+        let target = self.clone();
+        Self::spawn_poller(config.services_interval(), move || {
+            let data = overview_service.get_overview();
+            target.update(|s| s.overview = Some(Sample::new(data)));
+        });
+    }
+
+    /// Fetch the dynamic configuration again, e.g. right after changing it
+    pub fn refresh_dynamic_config(&self) {
+        let result = self.cluster_service.get_dynamic_config();
+        self.update(|s| store(&mut s.dynamic_config, &mut s.dynamic_config_error, result));
+    }
+
+    /// Start or stop polling the detail of a member. The first fetch happens
+    /// right away on the blocking pool instead of waiting for the next tick.
+    pub fn watch_member(&self, node: Option<NodeStatus>) {
+        let changed = {
+            let mut watched = self.watched_member.lock().unwrap_or_else(|e| e.into_inner());
+            let changed = watched.as_ref().map(|n| &n.name) != node.as_ref().map(|n| &n.name);
+            *watched = node;
+            changed
+        };
+
+        if changed {
+            self.update(|s| s.member_detail = None);
+            let target = self.clone();
+            tokio::task::spawn_blocking(move || target.refresh_member());
+        }
+    }
+
+    fn watched_member(&self) -> Option<NodeStatus> {
+        self.watched_member.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn refresh_member(&self) {
+        let Some(node) = self.watched_member() else {
+            return;
+        };
+        let status = self.cluster_service.get_member_status(&node);
+
+        // Drop the result if another member was opened while fetching
+        if self.watched_member().is_some_and(|n| n.name == node.name) {
+            self.update(|s| {
+                s.member_detail = Some(Sample::new(MemberDetail {
+                    name: node.name,
+                    api_url: node.api_url,
+                    status,
+                }))
+            });
+        }
+    }
+
+    /// Get a copy of the latest snapshot
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Snapshot)) {
+        let mut snapshot = self.snapshot.write().unwrap_or_else(|e| e.into_inner());
+        f(&mut snapshot);
+    }
+
+    /// Run `poll` every `period` on the blocking thread pool
+    fn spawn_poller<F>(period: Duration, poll: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let poll = Arc::new(poll);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+
+                // Patroni, systemctl and journalctl calls are all blocking
+                let poll = poll.clone();
+                let _ = tokio::task::spawn_blocking(move || poll()).await;
+            }
+        });
+    }
+}
+
+/// Store a fetch result: success replaces the last value and clears the error,
+/// failure keeps the last value so it can still be shown next to the error
+fn store<T>(
+    value: &mut Option<Sample<T>>,
+    error: &mut Option<Sample<PatroniError>>,
+    result: Result<T, PatroniError>,
+) {
+    match result {
+        Ok(v) => {
+            *value = Some(Sample::new(v));
+            *error = None;
+        }
+        Err(e) => *error = Some(Sample::new(e)),
+    }
+}
@@ -0,0 +1,185 @@
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// One row of the dynamic configuration tree
+#[derive(Debug, Clone)]
+pub struct ConfigEntry {
+    /// Keys from the root down to this entry
+    pub path: Vec<String>,
+    /// Leaf value; `None` for sections (objects)
+    pub value: Option<Value>,
+    pub collapsed: bool,
+}
+
+impl ConfigEntry {
+    pub fn depth(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    pub fn key(&self) -> &str {
+        self.path.last().map_or("", |k| k.as_str())
+    }
+
+    /// Dotted path, e.g. `postgresql.parameters.max_connections`
+    pub fn dotted_path(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+/// Flatten the `/config` document into visible tree rows, skipping the
+/// children of collapsed sections (identified by their dotted path)
+pub fn flatten_config(config: &Value, collapsed: &HashSet<String>) -> Vec<ConfigEntry> {
+    let mut entries = vec![];
+    if let Value::Object(map) = config {
+        flatten_into(map, &mut vec![], collapsed, &mut entries);
+    }
+
+    entries
+}
+
+fn flatten_into(
+    map: &Map<String, Value>,
+    path: &mut Vec<String>,
+    collapsed: &HashSet<String>,
+    entries: &mut Vec<ConfigEntry>,
+) {
+    for (key, value) in map {
+        path.push(key.clone());
+
+        match value {
+            Value::Object(children) => {
+                let is_collapsed = collapsed.contains(&path.join("."));
+                entries.push(ConfigEntry {
+                    path: path.clone(),
+                    value: None,
+                    collapsed: is_collapsed,
+                });
+                if !is_collapsed {
+                    flatten_into(children, path, collapsed, entries);
+                }
+            }
+            leaf => entries.push(ConfigEntry {
+                path: path.clone(),
+                value: Some(leaf.clone()),
+                collapsed: false,
+            }),
+        }
+
+        path.pop();
+    }
+}
+
+/// Render a value the way it is typed in the editor: strings without quotes,
+/// everything else as JSON
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Interpret user input as a new value for a setting.
+///
+/// String settings stay strings; other input is parsed as JSON (numbers,
+/// booleans, lists, `null` to remove the key) and falls back to a string.
+pub fn parse_config_value(input: &str, old: &Value) -> Value {
+    let input = input.trim();
+    if old.is_string() && input != "null" {
+        return Value::String(input.to_string());
+    }
+
+    serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.to_string()))
+}
+
+/// Build the `PATCH /config` body setting `value` at `path`
+pub fn config_patch(path: &[String], value: Value) -> Value {
+    path.iter()
+        .rev()
+        .fold(value, |inner, key| {
+            let mut map = Map::new();
+            map.insert(key.clone(), inner);
+            Value::Object(map)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> Value {
+        json!({
+            "loop_wait": 10,
+            "postgresql": {
+                "parameters": {"max_connections": 100, "shared_buffers": "256MB"},
+                "use_pg_rewind": true,
+            },
+            "ttl": 30,
+        })
+    }
+
+    fn path(dotted: &str) -> Vec<String> {
+        dotted.split('.').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn flattens_nested_parameters() {
+        let entries = flatten_config(&config(), &HashSet::new());
+
+        let rows: Vec<_> = entries.iter().map(|e| (e.dotted_path(), e.depth(), e.value.clone())).collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("loop_wait".to_string(), 0, Some(json!(10))),
+                ("postgresql".to_string(), 0, None),
+                ("postgresql.parameters".to_string(), 1, None),
+                ("postgresql.parameters.max_connections".to_string(), 2, Some(json!(100))),
+                ("postgresql.parameters.shared_buffers".to_string(), 2, Some(json!("256MB"))),
+                ("postgresql.use_pg_rewind".to_string(), 1, Some(json!(true))),
+                ("ttl".to_string(), 0, Some(json!(30))),
+            ]
+        );
+        assert_eq!(entries[3].key(), "max_connections");
+    }
+
+    #[test]
+    fn skips_children_of_collapsed_sections() {
+        let collapsed = HashSet::from(["postgresql.parameters".to_string()]);
+        let paths: Vec<_> = flatten_config(&config(), &collapsed).iter().map(ConfigEntry::dotted_path).collect();
+
+        assert_eq!(paths, ["loop_wait", "postgresql", "postgresql.parameters", "postgresql.use_pg_rewind", "ttl"]);
+    }
+
+    #[test]
+    fn parses_input_after_the_old_type() {
+        assert_eq!(parse_config_value(" 200 ", &json!(100)), json!(200));
+        assert_eq!(parse_config_value("0.5", &json!(1)), json!(0.5));
+        assert_eq!(parse_config_value("false", &json!(true)), json!(false));
+        assert_eq!(parse_config_value("[\"a\", \"b\"]", &json!([])), json!(["a", "b"]));
+        // String settings stay strings, even when they look like numbers
+        assert_eq!(parse_config_value("512MB", &json!("256MB")), json!("512MB"));
+        assert_eq!(parse_config_value("100", &json!("on")), json!("100"));
+        assert_eq!(parse_config_value("true", &json!("off")), json!("true"));
+        // Input that is not JSON falls back to a string
+        assert_eq!(parse_config_value("1GB", &json!(10)), json!("1GB"));
+    }
+
+    #[test]
+    fn null_removes_the_key() {
+        assert_eq!(parse_config_value("null", &json!("256MB")), Value::Null);
+        assert_eq!(parse_config_value("null", &json!(100)), Value::Null);
+        assert_eq!(
+            config_patch(&path("postgresql.parameters.shared_buffers"), Value::Null),
+            json!({"postgresql": {"parameters": {"shared_buffers": null}}})
+        );
+    }
+
+    #[test]
+    fn patch_touches_only_the_edited_key() {
+        assert_eq!(
+            config_patch(&path("postgresql.parameters.max_connections"), json!(200)),
+            json!({"postgresql": {"parameters": {"max_connections": 200}}})
+        );
+        assert_eq!(config_patch(&path("ttl"), json!(40)), json!({"ttl": 40}));
+    }
+}
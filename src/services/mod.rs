@@ -1,4 +1,12 @@
 pub mod actions;
+pub mod alerts;
+pub mod audit;
 pub mod cluster;
+pub mod collector;
+pub mod dynamic_config;
 pub mod logs;
 pub mod overview;
+pub mod preflight;
+pub mod rolling_restart;
+pub mod topology;
+pub mod verification;
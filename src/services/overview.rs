@@ -1,38 +1,41 @@
 use crate::config::Config;
-use crate::patroni::patroni::{ClusterInfo, Patroni};
 use crate::system;
+use serde::Serialize;
 use std::net::UdpSocket;
 
 pub struct OverviewService {
-    pub patroni_client: Patroni,
     pub config: Config,
 }
 
+#[derive(Debug, Clone)]
 pub struct OverviewData {
     pub hostname: String,
     pub ip: String,
-    pub cluster_data: ClusterInfo,
     pub components: Vec<ComponentStatus>,
+    pub haproxy_backends: (u32, u32),
+    pub vip: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
 pub struct ComponentStatus {
     pub name: String,
     pub up: bool,
     pub errors: u32,
     pub uptime: String,
+    /// Seconds since the service became active; `None` while it is down
+    pub uptime_secs: Option<u64>,
     pub version: String,
 }
 
 impl OverviewService {
-    pub fn new(patroni_client: Patroni, config: Config) -> Self {
-        OverviewService { patroni_client, config }
+    pub fn new(config: Config) -> Self {
+        OverviewService { config }
     }
 
-    /// Get overview data including hostname, IP, cluster info, and component statuses
+    /// Get overview data of the local host: hostname, IP, component statuses, HAProxy and VIP
     pub fn get_overview(&self) -> OverviewData {
         let hostname = self.get_hostname();
         let ip = self.get_local_ip();
-        let cluster_data = self.patroni_client.get_cluster_info();
 
         // Convert service names to string slices
         let service_names = self.config.services_list();
@@ -42,8 +45,9 @@ impl OverviewService {
         OverviewData {
             hostname,
             ip,
-            cluster_data,
             components,
+            haproxy_backends: self.fetch_haproxy_backend_stats(),
+            vip: system::detect_keepalived_vip(),
         }
     }
 
@@ -74,6 +78,7 @@ impl OverviewService {
                 let up = system::is_service_active(svc);
                 let errors = system::get_service_errors(svc, 300);
                 let uptime = system::get_service_uptime(svc);
+                let uptime_secs = up.then(|| system::get_service_uptime_secs(svc)).flatten();
                 let version = system::detect_service_version(svc);
 
                 ComponentStatus {
@@ -81,6 +86,7 @@ impl OverviewService {
                     up,
                     errors,
                     uptime,
+                    uptime_secs,
                     version,
                 }
             })
@@ -90,7 +96,7 @@ impl OverviewService {
     /// Fetch HAProxy backend statistics
     pub fn fetch_haproxy_backend_stats(&self) -> (u32, u32) {
         let result = system::query_haproxy_socket(&self.config.haproxy_socket, "show stat\n");
-        
+
         if let Ok(buf) = result {
             let mut up = 0u32;
             let mut total = 0u32;
@@ -115,9 +121,4 @@ impl OverviewService {
             (0, 0)
         }
     }
-
-    /// Detect Keepalived VIP (Virtual IP)
-    pub fn detect_keepalived_vip() -> String {
-        system::detect_keepalived_vip()
-    }
 }
\ No newline at end of file
@@ -0,0 +1,342 @@
+use crate::patroni::patroni::{ClusterInfo, NodeStatus, Patroni};
+use crate::services::actions::Action;
+use crate::services::cluster::ClusterService;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CheckStatus {
+    Pass,
+    /// Worth a second look, does not block the action
+    Warn,
+    /// Blocks the action unless overridden
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreflightCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl PreflightCheck {
+    fn new(name: &'static str, ok: bool, failure: CheckStatus, detail: String) -> Self {
+        PreflightCheck {
+            name,
+            status: if ok { CheckStatus::Pass } else { failure },
+            detail,
+        }
+    }
+}
+
+/// Whether any check blocks the action
+pub fn has_failures(checks: &[PreflightCheck]) -> bool {
+    checks.iter().any(|c| c.status == CheckStatus::Fail)
+}
+
+/// Check that `action` aimed at `target` (the switchover candidate or the
+/// member to reinitialize) is safe in the current cluster state. Blocking:
+/// the REST API of every other member is queried.
+pub fn run_checks(
+    cluster_service: &ClusterService,
+    action: &Action,
+    cluster: &ClusterInfo,
+    target: &NodeStatus,
+    max_lag_us: u64,
+) -> Vec<PreflightCheck> {
+    let mut checks = state_checks(action, cluster, target, max_lag_us);
+    if checks.is_empty() {
+        return checks;
+    }
+    checks.push(reachability_check(cluster_service, cluster, target));
+
+    checks
+}
+
+/// The checks decided by the cluster state alone, without asking any member.
+/// Empty for actions without pre-flight checks.
+pub fn state_checks(action: &Action, cluster: &ClusterInfo, target: &NodeStatus, max_lag_us: u64) -> Vec<PreflightCheck> {
+    let mut checks = vec![];
+
+    match action {
+        Action::Switchover => {
+            let streaming = matches!(target.state.as_str(), "running" | "streaming");
+            checks.push(PreflightCheck::new(
+                "Candidate streaming",
+                streaming,
+                CheckStatus::Fail,
+                format!("{} is {}", target.name, target.state),
+            ));
+            checks.push(match target.lag {
+                Some(lag) => PreflightCheck::new(
+                    "Candidate lag",
+                    lag <= max_lag_us,
+                    CheckStatus::Fail,
+                    format!("{} lags {lag}, limit {max_lag_us}", target.name),
+                ),
+                None => PreflightCheck::new(
+                    "Candidate lag",
+                    false,
+                    CheckStatus::Fail,
+                    format!("lag of {} is unknown", target.name),
+                ),
+            });
+            checks.push(PreflightCheck::new(
+                "Candidate can be promoted",
+                !target.tags.nofailover,
+                CheckStatus::Fail,
+                if target.tags.nofailover {
+                    format!("{} has the nofailover tag", target.name)
+                } else {
+                    format!("{} has no nofailover tag", target.name)
+                },
+            ));
+        }
+        Action::Reinitialize => {
+            let is_leader = cluster.is_leader(&target.name);
+            checks.push(PreflightCheck::new(
+                "Target is a replica",
+                !is_leader,
+                CheckStatus::Fail,
+                if is_leader {
+                    format!("{} is the leader", target.name)
+                } else {
+                    format!("{} is a {}", target.name, target.role)
+                },
+            ));
+            checks.push(PreflightCheck::new(
+                "Leader can serve a copy",
+                cluster.has_healthy_leader(),
+                CheckStatus::Fail,
+                match cluster.leader() {
+                    Some(leader) => format!("leader {} is {}", leader.name, leader.state),
+                    None => "the cluster has no leader".to_string(),
+                },
+            ));
+        }
+        _ => return checks,
+    }
+
+    checks.push(PreflightCheck::new(
+        "Cluster not paused",
+        !cluster.paused,
+        CheckStatus::Warn,
+        if cluster.paused {
+            "automatic failover is paused".to_string()
+        } else {
+            "automatic failover is active".to_string()
+        },
+    ));
+
+    let mut scheduled = vec![];
+    if let Some(switchover) = &cluster.scheduled_switchover {
+        scheduled.push(format!("switchover at {}", switchover.at));
+    }
+    for node in &cluster.members {
+        if let Some(restart) = &node.scheduled_restart {
+            scheduled.push(format!("restart of {} at {}", node.name, restart.schedule));
+        }
+    }
+    checks.push(PreflightCheck::new(
+        "No scheduled operation",
+        scheduled.is_empty(),
+        CheckStatus::Fail,
+        if scheduled.is_empty() {
+            "nothing is scheduled".to_string()
+        } else {
+            scheduled.join(", ")
+        },
+    ));
+
+    checks
+}
+
+/// Whether the REST API of every member but `target` answers
+fn reachability_check(cluster_service: &ClusterService, cluster: &ClusterInfo, target: &NodeStatus) -> PreflightCheck {
+    // The target itself may well be down, e.g. a replica being reinitialized
+    let others: Vec<&NodeStatus> = cluster.members.iter().filter(|n| n.name != target.name).collect();
+    let unreachable: Vec<String> = std::thread::scope(|scope| {
+        let probes: Vec<_> = others
+            .iter()
+            .map(|node| scope.spawn(|| cluster_service.get_member_status(node).err().map(|e| (node.name.clone(), e))))
+            .collect();
+        probes
+            .into_iter()
+            .filter_map(|probe| probe.join().ok().flatten())
+            .map(|(name, e)| format!("{name}: {}", e.kind()))
+            .collect()
+    });
+    PreflightCheck::new(
+        "Other members reachable",
+        unreachable.is_empty(),
+        CheckStatus::Fail,
+        if unreachable.is_empty() {
+            format!("{} of {} answered", others.len(), others.len())
+        } else {
+            unreachable.join(", ")
+        },
+    )
+}
+
+/// Checks run on the blocking thread pool for the confirmation dialog
+pub struct PendingChecks {
+    result: Arc<Mutex<Option<Vec<PreflightCheck>>>>,
+}
+
+impl PendingChecks {
+    pub fn start(
+        patroni_client: Patroni,
+        action: Action,
+        cluster: ClusterInfo,
+        target: NodeStatus,
+        max_lag_us: u64,
+    ) -> Self {
+        let result = Arc::new(Mutex::new(None));
+
+        let slot = result.clone();
+        tokio::task::spawn_blocking(move || {
+            let checks = run_checks(&ClusterService::new(patroni_client), &action, &cluster, &target, max_lag_us);
+            *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(checks);
+        });
+
+        PendingChecks { result }
+    }
+
+    /// Checks that could not run, failing so that the action needs the override
+    pub fn not_started(reason: String) -> Self {
+        let check = PreflightCheck::new("Pre-flight checks", false, CheckStatus::Fail, format!("not run: {reason}"));
+        PendingChecks { result: Arc::new(Mutex::new(Some(vec![check]))) }
+    }
+
+    /// The checks once they have all finished
+    pub fn result(&self) -> Option<Vec<PreflightCheck>> {
+        self.result.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patroni::patroni::ScheduledSwitchover;
+    use crate::patroni::testing::{cluster, member};
+    use serde_json::json;
+
+    const MAX_LAG_US: u64 = 1_000;
+
+    fn healthy() -> ClusterInfo {
+        cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({"lag": 0})),
+            member("pg3", "replica", json!({"lag": 500})),
+        ])
+    }
+
+    /// Status of every check by name
+    fn statuses(action: Action, cluster: &ClusterInfo, target: &str) -> Vec<(&'static str, CheckStatus)> {
+        state_checks(&action, cluster, &cluster.members_map[target], MAX_LAG_US)
+            .into_iter()
+            .map(|c| (c.name, c.status))
+            .collect()
+    }
+
+    fn status_of(checks: &[(&'static str, CheckStatus)], name: &str) -> CheckStatus {
+        checks.iter().find(|(n, _)| *n == name).map(|(_, s)| *s).unwrap()
+    }
+
+    #[test]
+    fn healthy_switchover_passes() {
+        let checks = statuses(Action::Switchover, &healthy(), "pg2");
+
+        assert_eq!(
+            checks.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+            [
+                "Candidate streaming",
+                "Candidate lag",
+                "Candidate can be promoted",
+                "Cluster not paused",
+                "No scheduled operation"
+            ]
+        );
+        assert!(checks.iter().all(|(_, status)| *status == CheckStatus::Pass));
+    }
+
+    #[test]
+    fn lagging_or_unknown_candidate_fails() {
+        let mut cluster = healthy();
+        cluster.members_map.insert("pg3".to_string(), member("pg3", "replica", json!({"lag": 5_000})));
+        let checks = statuses(Action::Switchover, &cluster, "pg3");
+        assert_eq!(status_of(&checks, "Candidate lag"), CheckStatus::Fail);
+
+        cluster.members_map.insert("pg3".to_string(), member("pg3", "replica", json!({"lag": "unknown"})));
+        let checks = statuses(Action::Switchover, &cluster, "pg3");
+        assert_eq!(status_of(&checks, "Candidate lag"), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn nofailover_candidate_fails() {
+        let mut cluster = healthy();
+        cluster.members_map.insert("pg2".to_string(), member("pg2", "replica", json!({"tags": {"nofailover": true}})));
+
+        let checks = statuses(Action::Switchover, &cluster, "pg2");
+        assert_eq!(status_of(&checks, "Candidate can be promoted"), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn paused_cluster_only_warns() {
+        let mut cluster = healthy();
+        cluster.paused = true;
+
+        let checks = state_checks(&Action::Switchover, &cluster, &cluster.members_map["pg2"], MAX_LAG_US);
+        assert_eq!(checks.iter().find(|c| c.name == "Cluster not paused").unwrap().status, CheckStatus::Warn);
+        assert!(!has_failures(&checks));
+    }
+
+    #[test]
+    fn pending_restart_or_switchover_fails() {
+        let mut cluster = healthy();
+        cluster.members[2] = member("pg3", "replica", json!({"scheduled_restart": {"schedule": "2026-01-01T00:00:00Z"}}));
+        let checks = state_checks(&Action::Switchover, &cluster, &cluster.members_map["pg2"], MAX_LAG_US);
+        let scheduled = checks.iter().find(|c| c.name == "No scheduled operation").unwrap();
+        assert_eq!(scheduled.status, CheckStatus::Fail);
+        assert_eq!(scheduled.detail, "restart of pg3 at 2026-01-01T00:00:00Z");
+
+        let mut cluster = healthy();
+        cluster.scheduled_switchover = Some(ScheduledSwitchover {
+            at: "2026-01-01T00:00:00Z".to_string(),
+            from: Some("pg1".to_string()),
+            to: None,
+        });
+        let checks = statuses(Action::Switchover, &cluster, "pg2");
+        assert_eq!(status_of(&checks, "No scheduled operation"), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn reinitialize_needs_a_healthy_leader() {
+        let checks = statuses(Action::Reinitialize, &healthy(), "pg3");
+        assert_eq!(status_of(&checks, "Target is a replica"), CheckStatus::Pass);
+        assert_eq!(status_of(&checks, "Leader can serve a copy"), CheckStatus::Pass);
+
+        let checks = statuses(Action::Reinitialize, &healthy(), "pg1");
+        assert_eq!(status_of(&checks, "Target is a replica"), CheckStatus::Fail);
+
+        let mut cluster = healthy();
+        cluster.leader_node_name = None;
+        let checks = statuses(Action::Reinitialize, &cluster, "pg3");
+        assert_eq!(status_of(&checks, "Leader can serve a copy"), CheckStatus::Fail);
+    }
+
+    #[test]
+    fn other_actions_have_no_checks() {
+        assert!(statuses(Action::Restart, &healthy(), "pg2").is_empty());
+    }
+}
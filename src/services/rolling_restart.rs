@@ -0,0 +1,456 @@
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, NodeStatus};
+use crate::services::actions::{ActionsService, RestartOptions};
+use crate::services::cluster::ClusterService;
+use anyhow::{Result, anyhow};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the cluster to become healthy again after a step
+const WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    /// Not started because the run was aborted or an earlier step failed
+    Skipped,
+}
+
+impl StepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepStatus::Pending => "pending",
+            StepStatus::Running => "running",
+            StepStatus::Done => "done",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RollingStep {
+    pub description: String,
+    pub status: StepStatus,
+}
+
+/// State of a rolling restart as shown in the progress view
+#[derive(Debug, Clone, Default)]
+pub struct RollingProgress {
+    pub steps: Vec<RollingStep>,
+    /// What the current step is doing or why the run stopped
+    pub message: String,
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Task {
+    Restart(String),
+    /// Hand leadership from this member to the best replica
+    Switchover(String),
+}
+
+impl Task {
+    fn description(&self) -> String {
+        match self {
+            Task::Restart(node) => format!("Restart {node}"),
+            Task::Switchover(leader) => format!("Switch over from {leader}"),
+        }
+    }
+}
+
+/// Restart of every member in turn: replicas one at a time, then a switchover
+/// and finally the former leader. Each step waits until all members stream
+/// again with lag under the limit before the next one starts.
+pub struct RollingRestart {
+    progress: Arc<Mutex<RollingProgress>>,
+    abort: Arc<AtomicBool>,
+}
+
+impl RollingRestart {
+    /// Plan the order from the current cluster state and run it on the
+    /// blocking thread pool
+    pub fn start(
+        actions_service: ActionsService,
+        cluster: &ClusterInfo,
+        max_lag_us: u64,
+        poll_interval: Duration,
+    ) -> Self {
+        let tasks = plan(cluster);
+        let progress = Arc::new(Mutex::new(RollingProgress {
+            steps: tasks
+                .iter()
+                .map(|task| RollingStep {
+                    description: task.description(),
+                    status: StepStatus::Pending,
+                })
+                .collect(),
+            message: "Starting".to_string(),
+            finished: false,
+        }));
+        let abort = Arc::new(AtomicBool::new(false));
+
+        let runner = Runner {
+            cluster_service: ClusterService::new(actions_service.patroni_client.clone()),
+            actions_service,
+            progress: progress.clone(),
+            abort: abort.clone(),
+            max_lag_us,
+            poll_interval,
+            wait_timeout: WAIT_TIMEOUT,
+        };
+        tokio::task::spawn_blocking(move || runner.run(&tasks, |step, task| runner.execute(step, task)));
+
+        RollingRestart { progress, abort }
+    }
+
+    /// Stop before the next step; a step already sent to Patroni completes
+    pub fn abort(&self) {
+        self.abort.store(true, Ordering::Relaxed);
+    }
+
+    pub fn progress(&self) -> RollingProgress {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.progress().finished
+    }
+}
+
+struct Runner {
+    actions_service: ActionsService,
+    cluster_service: ClusterService,
+    progress: Arc<Mutex<RollingProgress>>,
+    abort: Arc<AtomicBool>,
+    max_lag_us: u64,
+    poll_interval: Duration,
+    wait_timeout: Duration,
+}
+
+impl Runner {
+    /// Carry out `tasks` in order with `execute`, stopping on abort or at the
+    /// first failed step
+    fn run(&self, tasks: &[Task], mut execute: impl FnMut(usize, &Task) -> Result<()>) {
+        for (i, task) in tasks.iter().enumerate() {
+            if self.aborted() {
+                self.finish(i, "Aborted".to_string());
+                return;
+            }
+
+            self.update(|p| p.steps[i].status = StepStatus::Running);
+            match execute(i, task) {
+                Ok(()) => self.update(|p| p.steps[i].status = StepStatus::Done),
+                Err(e) => {
+                    self.update(|p| p.steps[i].status = StepStatus::Failed);
+                    self.finish(i + 1, format!("{e:#}"));
+                    return;
+                }
+            }
+        }
+
+        self.finish(tasks.len(), "Rolling restart completed".to_string());
+    }
+
+    fn execute(&self, step: usize, task: &Task) -> Result<()> {
+        let cluster = self.cluster_service.get_cluster_info()?;
+
+        match task {
+            Task::Restart(name) => {
+                let node = cluster
+                    .members_map
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Member {} is no longer in the cluster", name))?;
+
+                // Patroni answers once PostgreSQL is back up, which may take
+                // longer than the request timeout: a new start time tells
+                // that the restart happened
+                let started_before = self.start_time(node);
+
+                self.set_message(format!("Restarting {name}"));
+                if let Err(e) = self.actions_service.restart_node(node, &RestartOptions::default())
+                    && !timed_out(&e)
+                {
+                    return Err(e);
+                }
+
+                self.wait_until(&format!("{name} to restart and stream with lag under the limit"), |c| {
+                    c.members_map.get(name).is_some_and(|n| {
+                        matches!(n.state.as_str(), "running" | "streaming")
+                            && started_before.as_ref().is_none_or(|before| {
+                                self.start_time(n).is_some_and(|started| started != *before)
+                            })
+                    }) && c.has_healthy_leader()
+                        && c.replication_ok(self.max_lag_us)
+                })
+            }
+            Task::Switchover(leader) => {
+                let candidate = cluster
+                    .failover_candidates()
+                    .first()
+                    .map(|n| n.name.clone())
+                    .ok_or_else(|| anyhow!("No replica is eligible to take over from {}", leader))?;
+
+                self.update(|p| p.steps[step].description = format!("Switch over from {leader} to {candidate}"));
+                self.set_message(format!("Switching over to {candidate}"));
+                if let Err(e) = self.actions_service.switchover(leader, &candidate, None)
+                    && !timed_out(&e)
+                {
+                    return Err(e);
+                }
+
+                self.wait_until(&format!("{candidate} to lead the cluster"), |c| {
+                    c.is_leader(&candidate) && c.has_healthy_leader()
+                })
+            }
+        }
+    }
+
+    /// Poll the cluster until `done` holds
+    fn wait_until(&self, what: &str, done: impl Fn(&ClusterInfo) -> bool) -> Result<()> {
+        self.poll_until(what, || self.cluster_service.get_cluster_info(), done)
+    }
+
+    /// Call `poll` until the state it returns satisfies `done`. Fetch errors
+    /// are expected while members restart and are only reported.
+    fn poll_until(
+        &self,
+        what: &str,
+        mut poll: impl FnMut() -> Result<ClusterInfo, PatroniError>,
+        done: impl Fn(&ClusterInfo) -> bool,
+    ) -> Result<()> {
+        let started = Instant::now();
+
+        loop {
+            let state = match poll() {
+                Ok(cluster) if done(&cluster) => return Ok(()),
+                Ok(_) => String::new(),
+                Err(e) => format!(" (last poll: {})", e.kind()),
+            };
+
+            if self.aborted() {
+                return Err(anyhow!("Aborted while waiting for {}", what));
+            }
+            if started.elapsed() > self.wait_timeout {
+                return Err(anyhow!("Timed out after {}s waiting for {}", self.wait_timeout.as_secs(), what));
+            }
+
+            self.set_message(format!(
+                "Waiting for {what}, {}s{state}",
+                started.elapsed().as_secs()
+            ));
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+
+    /// When PostgreSQL on `node` was last started, if it can be asked
+    fn start_time(&self, node: &NodeStatus) -> Option<String> {
+        self.cluster_service
+            .get_member_status(node)
+            .ok()
+            .and_then(|status| status.postmaster_start_time)
+    }
+
+    fn aborted(&self) -> bool {
+        self.abort.load(Ordering::Relaxed)
+    }
+
+    /// Mark the steps from `first_skipped` on as skipped and stop
+    fn finish(&self, first_skipped: usize, message: String) {
+        self.update(|p| {
+            for step in p.steps.iter_mut().skip(first_skipped) {
+                step.status = StepStatus::Skipped;
+            }
+            p.message = message;
+            p.finished = true;
+        });
+    }
+
+    fn set_message(&self, message: String) {
+        self.update(|p| p.message = message);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut RollingProgress)) {
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut progress);
+    }
+}
+
+/// Replicas one at a time in member order, then a switchover away from the
+/// leader and finally the former leader
+fn plan(cluster: &ClusterInfo) -> Vec<Task> {
+    let mut tasks: Vec<Task> = cluster
+        .members
+        .iter()
+        .filter(|n| !cluster.is_leader(&n.name))
+        .map(|n| Task::Restart(n.name.clone()))
+        .collect();
+    if let Some(leader) = &cluster.leader_node_name {
+        tasks.push(Task::Switchover(leader.clone()));
+        tasks.push(Task::Restart(leader.clone()));
+    }
+    tasks
+}
+
+/// Whether the request failed because its answer took too long, so it may
+/// well have been carried out
+fn timed_out(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|e| matches!(e.downcast_ref::<PatroniError>(), Some(PatroniError::Timeout { .. })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patroni::patroni::{ClientOptions, Patroni};
+    use crate::patroni::testing::{cluster, member};
+    use serde_json::json;
+
+    fn three_members() -> ClusterInfo {
+        cluster(vec![
+            member("pg2", "replica", json!({"lag": 0})),
+            member("pg1", "leader", json!({})),
+            member("pg3", "replica", json!({"lag": 0})),
+        ])
+    }
+
+    /// Runner of `tasks` that never reaches Patroni: steps are carried out
+    /// by the closure given to `run`
+    fn runner(tasks: &[Task]) -> Runner {
+        let patroni_client = Patroni::new(vec![], &ClientOptions::default()).unwrap();
+        Runner {
+            actions_service: ActionsService::new(patroni_client.clone(), None),
+            cluster_service: ClusterService::new(patroni_client),
+            progress: Arc::new(Mutex::new(RollingProgress {
+                steps: tasks
+                    .iter()
+                    .map(|task| RollingStep {
+                        description: task.description(),
+                        status: StepStatus::Pending,
+                    })
+                    .collect(),
+                ..Default::default()
+            })),
+            abort: Arc::new(AtomicBool::new(false)),
+            max_lag_us: 1_000,
+            poll_interval: Duration::ZERO,
+            wait_timeout: Duration::ZERO,
+        }
+    }
+
+    fn statuses(runner: &Runner) -> Vec<StepStatus> {
+        runner.progress.lock().unwrap().steps.iter().map(|s| s.status).collect()
+    }
+
+    #[test]
+    fn replicas_restart_before_the_leader() {
+        let descriptions: Vec<String> = plan(&three_members()).iter().map(Task::description).collect();
+
+        assert_eq!(descriptions, ["Restart pg2", "Restart pg3", "Switch over from pg1", "Restart pg1"]);
+    }
+
+    #[test]
+    fn cluster_without_leader_only_restarts_replicas() {
+        let cluster = cluster(vec![member("pg2", "replica", json!({})), member("pg3", "replica", json!({}))]);
+        let descriptions: Vec<String> = plan(&cluster).iter().map(Task::description).collect();
+
+        assert_eq!(descriptions, ["Restart pg2", "Restart pg3"]);
+    }
+
+    #[test]
+    fn steps_run_in_order_to_completion() {
+        let tasks = plan(&three_members());
+        let runner = runner(&tasks);
+        let mut executed = vec![];
+
+        runner.run(&tasks, |step, task| {
+            executed.push((step, task.description()));
+            Ok(())
+        });
+
+        assert_eq!(executed.iter().map(|(step, _)| *step).collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(statuses(&runner), [StepStatus::Done; 4]);
+        let progress = runner.progress.lock().unwrap().clone();
+        assert!(progress.finished);
+        assert_eq!(progress.message, "Rolling restart completed");
+    }
+
+    #[test]
+    fn abort_stops_before_the_next_step() {
+        let tasks = plan(&three_members());
+        let runner = runner(&tasks);
+        let mut executed = 0;
+
+        runner.run(&tasks, |_, _| {
+            executed += 1;
+            // The step in progress completes, the next one does not start
+            runner.abort.store(true, Ordering::Relaxed);
+            Ok(())
+        });
+
+        assert_eq!(executed, 1);
+        assert_eq!(
+            statuses(&runner),
+            [StepStatus::Done, StepStatus::Skipped, StepStatus::Skipped, StepStatus::Skipped]
+        );
+        assert_eq!(runner.progress.lock().unwrap().message, "Aborted");
+    }
+
+    #[test]
+    fn failed_step_skips_the_rest() {
+        let tasks = plan(&three_members());
+        let runner = runner(&tasks);
+
+        runner.run(&tasks, |step, _| match step {
+            1 => Err(anyhow!("pg3 did not come back")),
+            _ => Ok(()),
+        });
+
+        assert_eq!(
+            statuses(&runner),
+            [StepStatus::Done, StepStatus::Failed, StepStatus::Skipped, StepStatus::Skipped]
+        );
+        let progress = runner.progress.lock().unwrap().clone();
+        assert!(progress.finished);
+        assert_eq!(progress.message, "pg3 did not come back");
+    }
+
+    #[test]
+    fn wait_timeout_fails_the_step() {
+        let tasks = plan(&three_members());
+        let runner = runner(&tasks);
+        // pg2 never streams again
+        let stopped = cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({"state": "stopped"})),
+            member("pg3", "replica", json!({"lag": 0})),
+        ]);
+
+        runner.run(&tasks, |_, _| {
+            runner.poll_until("pg2 to stream", || Ok(stopped.clone()), |c| c.replication_ok(runner.max_lag_us))
+        });
+
+        assert_eq!(
+            statuses(&runner),
+            [StepStatus::Failed, StepStatus::Skipped, StepStatus::Skipped, StepStatus::Skipped]
+        );
+        assert_eq!(runner.progress.lock().unwrap().message, "Timed out after 0s waiting for pg2 to stream");
+    }
+
+    #[test]
+    fn wait_ends_once_the_cluster_is_healthy() {
+        let mut runner = runner(&[]);
+        runner.wait_timeout = WAIT_TIMEOUT;
+        // Popped from the end: a failed poll while members restart, then a healthy cluster
+        let mut polls = vec![Ok(three_members()), Err(PatroniError::Timeout { url: "/cluster".to_string() })];
+
+        let result = runner.poll_until("the cluster", || polls.pop().unwrap(), |c| c.replication_ok(runner.max_lag_us));
+
+        assert!(result.is_ok());
+        assert!(polls.is_empty());
+    }
+}
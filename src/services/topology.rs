@@ -0,0 +1,270 @@
+use crate::patroni::patroni::{ClusterInfo, NodeStatus};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    Off,
+    On,
+    /// Quorum-based commit (Patroni 4)
+    Quorum,
+}
+
+impl SyncMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncMode::Off => "off",
+            SyncMode::On => "on",
+            SyncMode::Quorum => "quorum",
+        }
+    }
+}
+
+/// Synchronous replication settings of the dynamic configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncSettings {
+    pub mode: SyncMode,
+    /// Commits wait rather than fall back to asynchronous replication when
+    /// no synchronous standby is available
+    pub strict: bool,
+    /// Number of synchronous standbys; Patroni defaults to 1
+    pub node_count: u64,
+}
+
+impl SyncSettings {
+    /// Read the settings from the `/config` document
+    pub fn from_config(config: &Value) -> Self {
+        let mode = match config.get("synchronous_mode") {
+            Some(Value::Bool(true)) => SyncMode::On,
+            Some(Value::String(s)) if s == "quorum" => SyncMode::Quorum,
+            Some(Value::String(s)) if matches!(s.as_str(), "on" | "true") => SyncMode::On,
+            _ => SyncMode::Off,
+        };
+
+        SyncSettings {
+            mode,
+            strict: config
+                .get("synchronous_mode_strict")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            node_count: config
+                .get("synchronous_node_count")
+                .and_then(Value::as_u64)
+                .unwrap_or(1),
+        }
+    }
+}
+
+/// Whether the member is a synchronous standby of the leader
+pub fn is_sync_standby(node: &NodeStatus) -> bool {
+    matches!(node.role.as_str(), "sync_standby" | "quorum_standby")
+}
+
+/// Replicas the leader can pick as synchronous standbys: streaming
+/// directly from it and not tagged `nosync`
+pub fn sync_candidates(cluster: &ClusterInfo) -> Vec<&NodeStatus> {
+    let Some(leader) = &cluster.leader_node_name else {
+        return vec![];
+    };
+    cluster
+        .members
+        .iter()
+        .filter(|n| n.name != *leader && !n.tags.nosync)
+        .filter(|n| matches!(n.state.as_str(), "running" | "streaming"))
+        .filter(|n| n.tags.replicatefrom.as_ref().is_none_or(|source| source == leader))
+        .collect()
+}
+
+/// A member placed in the replication tree
+#[derive(Debug, Clone)]
+pub struct TopologyEntry {
+    pub node: NodeStatus,
+    /// For each level above the member: whether the branch at that level is
+    /// the last one of its parent. Its length is the member's depth.
+    pub last_at_level: Vec<bool>,
+    /// Why the member is not placed where its `replicatefrom` tag says
+    pub note: Option<String>,
+}
+
+impl TopologyEntry {
+    pub fn depth(&self) -> usize {
+        self.last_at_level.len()
+    }
+}
+
+/// Members as a replication tree in drawing order: the leader first, then
+/// each member followed by the replicas streaming from it. Replicas go
+/// under their `replicatefrom` member, or under the leader when that member
+/// is unknown or the tags form a cycle. Sync standbys come first among
+/// siblings.
+pub fn build(cluster: &ClusterInfo) -> Vec<TopologyEntry> {
+    let leader = cluster.leader().map(|n| n.name.as_str());
+
+    // Upstream of every member as its tags configure it
+    let mut upstreams: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+    let mut notes: BTreeMap<&str, String> = BTreeMap::new();
+    for node in &cluster.members {
+        let upstream = match node.tags.replicatefrom.as_deref() {
+            _ if Some(node.name.as_str()) == leader => None,
+            Some(source) if source != node.name && cluster.members_map.contains_key(source) => Some(source),
+            Some(source) => {
+                notes.insert(&node.name, format!("replicatefrom {source} is not a member"));
+                leader
+            }
+            None => leader,
+        };
+        upstreams.insert(&node.name, upstream);
+    }
+
+    // Cascading back to itself: Patroni streams such a member from the leader
+    for node in &cluster.members {
+        let mut current = upstreams[node.name.as_str()];
+        for _ in 0..cluster.members.len() {
+            match current {
+                Some(name) if name == node.name => {
+                    notes.insert(&node.name, "replicatefrom forms a cycle".to_string());
+                    upstreams.insert(&node.name, leader);
+                    break;
+                }
+                Some(name) => current = upstreams.get(name).copied().flatten(),
+                None => break,
+            }
+        }
+    }
+
+    let mut children: BTreeMap<Option<&str>, Vec<&NodeStatus>> = BTreeMap::new();
+    for node in &cluster.members {
+        children.entry(upstreams[node.name.as_str()]).or_default().push(node);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|n| (!is_sync_standby(n), n.name.clone()));
+    }
+
+    let mut entries = vec![];
+    let mut placed = HashSet::new();
+    let roots = children.get(&None).cloned().unwrap_or_default();
+    for root in roots {
+        place(root, vec![], &children, &notes, &mut placed, &mut entries);
+    }
+
+    entries
+}
+
+fn place<'a>(
+    node: &'a NodeStatus,
+    last_at_level: Vec<bool>,
+    children: &BTreeMap<Option<&'a str>, Vec<&'a NodeStatus>>,
+    notes: &BTreeMap<&str, String>,
+    placed: &mut HashSet<&'a str>,
+    entries: &mut Vec<TopologyEntry>,
+) {
+    if !placed.insert(node.name.as_str()) {
+        return;
+    }
+    entries.push(TopologyEntry {
+        node: node.clone(),
+        last_at_level: last_at_level.clone(),
+        note: notes.get(node.name.as_str()).cloned(),
+    });
+
+    let replicas = children.get(&Some(node.name.as_str())).map_or(&[][..], Vec::as_slice);
+    for (i, replica) in replicas.iter().enumerate() {
+        let mut levels = last_at_level.clone();
+        levels.push(i + 1 == replicas.len());
+        place(replica, levels, children, notes, placed, entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patroni::testing::{cluster, member};
+    use serde_json::json;
+
+    /// Name, depth and note of every entry in drawing order
+    fn tree(cluster: &ClusterInfo) -> Vec<(String, usize, Option<String>)> {
+        build(cluster).into_iter().map(|e| (e.node.name.clone(), e.depth(), e.note)).collect()
+    }
+
+    #[test]
+    fn cascading_chain() {
+        let cluster = cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({})),
+            member("pg3", "replica", json!({"tags": {"replicatefrom": "pg2"}})),
+            member("pg4", "replica", json!({"tags": {"replicatefrom": "pg3"}})),
+        ]);
+
+        assert_eq!(
+            tree(&cluster),
+            vec![
+                ("pg1".to_string(), 0, None),
+                ("pg2".to_string(), 1, None),
+                ("pg3".to_string(), 2, None),
+                ("pg4".to_string(), 3, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn cycle_falls_back_to_the_leader() {
+        let cluster = cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({"tags": {"replicatefrom": "pg3"}})),
+            member("pg3", "replica", json!({"tags": {"replicatefrom": "pg2"}})),
+        ]);
+
+        assert_eq!(
+            tree(&cluster),
+            vec![
+                ("pg1".to_string(), 0, None),
+                ("pg2".to_string(), 1, Some("replicatefrom forms a cycle".to_string())),
+                ("pg3".to_string(), 2, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_source_falls_back_to_the_leader() {
+        let cluster = cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({"tags": {"replicatefrom": "pg9"}})),
+        ]);
+
+        assert_eq!(
+            tree(&cluster),
+            vec![
+                ("pg1".to_string(), 0, None),
+                ("pg2".to_string(), 1, Some("replicatefrom pg9 is not a member".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_standbys_sort_first() {
+        let cluster = cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({})),
+            member("pg3", "sync_standby", json!({})),
+        ]);
+
+        let entries = build(&cluster);
+        let order: Vec<_> = entries.iter().map(|e| e.node.name.as_str()).collect();
+        assert_eq!(order, ["pg1", "pg3", "pg2"]);
+        assert_eq!(entries[1].last_at_level, [false]);
+        assert_eq!(entries[2].last_at_level, [true]);
+    }
+
+    #[test]
+    fn sync_candidates_stream_from_the_leader() {
+        let cluster = cluster(vec![
+            member("pg1", "leader", json!({})),
+            member("pg2", "replica", json!({})),
+            member("pg3", "replica", json!({"tags": {"nosync": true}})),
+            member("pg4", "replica", json!({"tags": {"replicatefrom": "pg2"}})),
+        ]);
+
+        let names: Vec<_> = sync_candidates(&cluster).iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["pg2"]);
+    }
+}
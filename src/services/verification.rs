@@ -0,0 +1,551 @@
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, NodeStatus, Patroni};
+use crate::services::actions::Action;
+use crate::services::cluster::ClusterService;
+use crate::services::rolling_restart::StepStatus;
+use crate::services::topology;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the cluster to show the outcome of an action
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// What the cluster has to show before a step counts as reached
+#[derive(Debug, Clone)]
+enum Expectation {
+    /// The member leads the cluster and is running
+    Leader {
+        node: String,
+        /// Leader when the action was sent, which may hold on for a while
+        previous: Option<String>,
+    },
+    /// PostgreSQL on the member was started after the action was sent
+    Restarted {
+        node: String,
+        /// `postmaster_start_time` before the action; `None` if it was unknown
+        started_before: Option<String>,
+    },
+    /// The member is running, or streaming if it is a replica
+    Running(String),
+    /// The member is a replica streaming with lag under the limit
+    Replicating(String),
+    Paused(bool),
+    SwitchoverScheduled(bool),
+    RestartScheduled(String, bool),
+    /// Number of members in a synchronous standby role
+    SyncStandbys { min: usize, max: usize },
+}
+
+/// States in which a member will not come back without intervention
+fn is_failed_state(state: &str) -> bool {
+    state == "crashed" || state.ends_with("failed")
+}
+
+#[derive(Debug, Clone)]
+pub struct VerificationStep {
+    pub description: String,
+    pub status: StepStatus,
+}
+
+/// Progress of the follow-up of an action as shown in the progress panel
+#[derive(Debug, Clone)]
+pub struct VerificationProgress {
+    /// What was done, e.g. `Switchover to pg2`
+    pub title: String,
+    pub steps: Vec<VerificationStep>,
+    /// What is being waited for, or the verdict once finished
+    pub message: String,
+    pub finished: bool,
+    /// Every step was reached before the timeout
+    pub succeeded: bool,
+}
+
+/// The outcome an action should lead to, worked out before it is sent
+pub struct VerificationPlan {
+    title: String,
+    steps: Vec<(String, Expectation)>,
+}
+
+impl VerificationPlan {
+    /// Plan the follow-up of `action` aimed at `target` (the candidate of a
+    /// switchover or failover). Actions without an observable outcome, and
+    /// the rolling restart which follows itself, have no plan. Blocking: the
+    /// REST API of a member about to restart is queried for its start time.
+    pub fn new(
+        cluster_service: &ClusterService,
+        action: &Action,
+        cluster: &ClusterInfo,
+        target: Option<&NodeStatus>,
+        scheduled: bool,
+    ) -> Option<Self> {
+        let leader = &cluster.leader_node_name;
+        let new_leader = |node: &NodeStatus| Expectation::Leader {
+            node: node.name.clone(),
+            previous: leader.clone(),
+        };
+        let restarted = |node: &NodeStatus| Expectation::Restarted {
+            node: node.name.clone(),
+            started_before: cluster_service
+                .get_member_status(node)
+                .ok()
+                .and_then(|s| s.postmaster_start_time),
+        };
+
+        let (title, steps) = match (action, target) {
+            (Action::Switchover, Some(candidate)) if scheduled => (
+                format!("Scheduled switchover to {}", candidate.name),
+                vec![(
+                    "Switchover is scheduled".to_string(),
+                    Expectation::SwitchoverScheduled(true),
+                )],
+            ),
+            (Action::Switchover, Some(candidate)) => {
+                let mut steps = vec![(format!("{} leads the cluster", candidate.name), new_leader(candidate))];
+                if let Some(leader) = leader {
+                    steps.push((
+                        format!("{leader} streams as a replica"),
+                        Expectation::Replicating(leader.clone()),
+                    ));
+                }
+                (
+                    match leader {
+                        Some(leader) => format!("Switchover from {leader} to {}", candidate.name),
+                        None => format!("Switchover to {}", candidate.name),
+                    },
+                    steps,
+                )
+            }
+            (Action::Failover, Some(candidate)) => (
+                format!("Failover to {}", candidate.name),
+                vec![(format!("{} leads the cluster", candidate.name), new_leader(candidate))],
+            ),
+            (Action::Restart, Some(node)) if scheduled => (
+                format!("Scheduled restart of {}", node.name),
+                vec![(
+                    "Restart is scheduled".to_string(),
+                    Expectation::RestartScheduled(node.name.clone(), true),
+                )],
+            ),
+            (Action::Restart, Some(node)) => (
+                format!("Restart of {}", node.name),
+                vec![
+                    (format!("{} was restarted", node.name), restarted(node)),
+                    (
+                        format!("{} is {}", node.name, if cluster.is_leader(&node.name) { "running" } else { "streaming" }),
+                        Expectation::Running(node.name.clone()),
+                    ),
+                ],
+            ),
+            (Action::Reinitialize, Some(node)) => (
+                format!("Reinitialize of {}", node.name),
+                vec![
+                    (format!("{} was rebuilt and started", node.name), restarted(node)),
+                    (
+                        format!("{} streams with lag under the limit", node.name),
+                        Expectation::Replicating(node.name.clone()),
+                    ),
+                ],
+            ),
+            (Action::PauseCluster, _) => (
+                format!("Pause of {}", cluster.scope),
+                vec![("Cluster is paused".to_string(), Expectation::Paused(true))],
+            ),
+            (Action::ResumeCluster, _) => (
+                format!("Resume of {}", cluster.scope),
+                vec![("Cluster is no longer paused".to_string(), Expectation::Paused(false))],
+            ),
+            (Action::CancelScheduledSwitchover, _) => (
+                "Cancel of the scheduled switchover".to_string(),
+                vec![(
+                    "No switchover is scheduled".to_string(),
+                    Expectation::SwitchoverScheduled(false),
+                )],
+            ),
+            (Action::EnableSyncMode, _) => (
+                format!("Enabling synchronous mode on {}", cluster.scope),
+                match topology::sync_candidates(cluster).len() {
+                    0 => vec![],
+                    _ => vec![(
+                        "A synchronous standby is chosen".to_string(),
+                        Expectation::SyncStandbys { min: 1, max: usize::MAX },
+                    )],
+                },
+            ),
+            (Action::DisableSyncMode, _) => (
+                format!("Disabling synchronous mode on {}", cluster.scope),
+                vec![(
+                    "No member is a synchronous standby".to_string(),
+                    Expectation::SyncStandbys { min: 0, max: 0 },
+                )],
+            ),
+            (Action::CancelScheduledRestart, Some(node)) => (
+                format!("Cancel of the scheduled restart of {}", node.name),
+                vec![(
+                    format!("No restart of {} is scheduled", node.name),
+                    Expectation::RestartScheduled(node.name.clone(), false),
+                )],
+            ),
+            _ => return None,
+        };
+
+        Some(VerificationPlan { title, steps })
+    }
+
+    /// Plan the follow-up of setting `synchronous_node_count` to `count`: as
+    /// many standbys as there are candidates for it become synchronous
+    pub fn sync_node_count(cluster: &ClusterInfo, count: u64) -> Self {
+        let expected = topology::sync_candidates(cluster)
+            .len()
+            .min(usize::try_from(count).unwrap_or(usize::MAX));
+
+        VerificationPlan {
+            title: format!("synchronous_node_count {count} on {}", cluster.scope),
+            steps: vec![(
+                format!("{expected} synchronous standby(s)"),
+                Expectation::SyncStandbys {
+                    min: expected,
+                    max: expected,
+                },
+            )],
+        }
+    }
+
+    /// Follow the cluster on the blocking thread pool until every step is
+    /// reached, one fails or the timeout expires. Call once the action was
+    /// accepted by Patroni.
+    pub fn start(self, patroni_client: Patroni, max_lag_us: u64, poll_interval: Duration) -> Verification {
+        let progress = Arc::new(Mutex::new(VerificationProgress {
+            title: self.title,
+            steps: self
+                .steps
+                .iter()
+                .map(|(description, _)| VerificationStep {
+                    description: description.clone(),
+                    status: StepStatus::Pending,
+                })
+                .collect(),
+            message: "Waiting for the first poll".to_string(),
+            finished: false,
+            succeeded: false,
+        }));
+
+        let follower = Follower {
+            cluster_service: ClusterService::new(patroni_client),
+            progress: progress.clone(),
+            max_lag_us,
+            poll_interval,
+            timeout: VERIFY_TIMEOUT,
+        };
+        let expectations = self.steps.into_iter().map(|(_, expectation)| expectation).collect();
+        let slot = progress.clone();
+        tokio::task::spawn_blocking(move || {
+            let follow = || follower.run(expectations, || follower.cluster_service.get_cluster_info());
+            // Callers poll until `finished`, which must be set however following ends
+            if std::panic::catch_unwind(AssertUnwindSafe(follow)).is_err() {
+                let mut progress = slot.lock().unwrap_or_else(|e| e.into_inner());
+                progress.message = "Not verified: following the cluster stopped on an internal error".to_string();
+                progress.finished = true;
+                progress.succeeded = false;
+            }
+        });
+
+        Verification { progress }
+    }
+}
+
+/// Follow-up of an action running in the background
+pub struct Verification {
+    progress: Arc<Mutex<VerificationProgress>>,
+}
+
+impl Verification {
+    pub fn progress(&self) -> VerificationProgress {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+struct Follower {
+    cluster_service: ClusterService,
+    progress: Arc<Mutex<VerificationProgress>>,
+    max_lag_us: u64,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl Follower {
+    /// Check the states returned by `poll` against `expectations` in order
+    /// until all are reached, one cannot be or the timeout expires
+    fn run(&self, expectations: Vec<Expectation>, mut poll: impl FnMut() -> Result<ClusterInfo, PatroniError>) {
+        let started = Instant::now();
+        let mut current = 0;
+
+        loop {
+            match poll() {
+                Ok(cluster) => {
+                    // Several steps may be reached between two polls
+                    while let Some(expectation) = expectations.get(current) {
+                        match self.check(expectation, &cluster) {
+                            Ok(true) => {
+                                self.update(|p| p.steps[current].status = StepStatus::Done);
+                                current += 1;
+                            }
+                            Ok(false) => break,
+                            Err(reason) => {
+                                self.finish(current, false, format!("Not verified: {reason}"));
+                                return;
+                            }
+                        }
+                    }
+                    if current == expectations.len() {
+                        self.finish(current, true, format!("Verified after {}s", started.elapsed().as_secs()));
+                        return;
+                    }
+                    self.update(|p| {
+                        p.steps[current].status = StepStatus::Running;
+                        p.message = format!(
+                            "Waiting for: {}, {}s",
+                            p.steps[current].description,
+                            started.elapsed().as_secs()
+                        );
+                    });
+                }
+                // Expected while members restart, only reported
+                Err(e) => self.update(|p| {
+                    p.message = format!("Waiting, {}s (last poll: {})", started.elapsed().as_secs(), e.kind())
+                }),
+            }
+
+            if started.elapsed() > self.timeout {
+                let waiting_for = self
+                    .progress()
+                    .steps
+                    .get(current)
+                    .map_or_else(|| "the cluster state".to_string(), |step| step.description.clone());
+                self.finish(
+                    current,
+                    false,
+                    format!("Not verified: timed out after {}s waiting for: {waiting_for}", self.timeout.as_secs()),
+                );
+                return;
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+
+    /// Whether the cluster shows `expectation`, or why it never will
+    fn check(&self, expectation: &Expectation, cluster: &ClusterInfo) -> Result<bool, String> {
+        let member = |name: &str| cluster.members_map.get(name);
+        let failed = |name: &str| match member(name) {
+            Some(node) if is_failed_state(&node.state) => Err(format!("{name} is in state {}", node.state)),
+            _ => Ok(()),
+        };
+
+        match expectation {
+            Expectation::Leader { node, previous } => {
+                failed(node)?;
+                // A third member taking over means Patroni chose another candidate;
+                // no leader at all is expected while it changes hands
+                if let Some(leader) = &cluster.leader_node_name
+                    && leader != node
+                    && Some(leader) != previous.as_ref()
+                {
+                    return Err(format!("{leader} leads the cluster instead of {node}"));
+                }
+                Ok(cluster.is_leader(node) && cluster.has_healthy_leader())
+            }
+            Expectation::Restarted { node, started_before } => {
+                failed(node)?;
+                let Some(node) = member(node) else {
+                    return Ok(false);
+                };
+                // Not answering is expected while PostgreSQL is down
+                let Ok(status) = self.cluster_service.get_member_status(node) else {
+                    return Ok(false);
+                };
+                let started = status.postmaster_start_time;
+                Ok(status.state.as_deref() == Some("running")
+                    && started.is_some()
+                    && (started_before.is_none() || started != *started_before))
+            }
+            Expectation::Running(name) => {
+                failed(name)?;
+                Ok(member(name).is_some_and(|n| matches!(n.state.as_str(), "running" | "streaming")))
+            }
+            Expectation::Replicating(name) => {
+                failed(name)?;
+                Ok(member(name).is_some_and(|n| {
+                    !cluster.is_leader(&n.name)
+                        && matches!(n.state.as_str(), "running" | "streaming")
+                        && n.lag.is_some_and(|lag| lag <= self.max_lag_us)
+                }))
+            }
+            Expectation::Paused(paused) => Ok(cluster.paused == *paused),
+            Expectation::SwitchoverScheduled(scheduled) => Ok(cluster.scheduled_switchover.is_some() == *scheduled),
+            Expectation::RestartScheduled(name, scheduled) => {
+                Ok(member(name).is_some_and(|n| n.scheduled_restart.is_some() == *scheduled))
+            }
+            Expectation::SyncStandbys { min, max } => {
+                let count = cluster.members.iter().filter(|n| topology::is_sync_standby(n)).count();
+                Ok((*min..=*max).contains(&count))
+            }
+        }
+    }
+
+    /// Record the verdict; the step at `stopped_at` failed unless all succeeded
+    fn finish(&self, stopped_at: usize, succeeded: bool, message: String) {
+        self.update(|p| {
+            for (i, step) in p.steps.iter_mut().enumerate().skip(stopped_at) {
+                step.status = if i == stopped_at { StepStatus::Failed } else { StepStatus::Skipped };
+            }
+            p.message = message;
+            p.finished = true;
+            p.succeeded = succeeded;
+        });
+    }
+
+    fn progress(&self) -> VerificationProgress {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut VerificationProgress)) {
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::patroni::patroni::ClientOptions;
+    use crate::patroni::testing::{cluster, member};
+    use serde_json::{Value, json};
+
+    /// pg1 leading pg2 and pg3, with the fields of `pg1`/`pg2` merged in
+    fn led_by_pg1(pg1: Value, pg2: Value) -> ClusterInfo {
+        cluster(vec![
+            member("pg1", "leader", pg1),
+            member("pg2", "replica", pg2),
+            member("pg3", "replica", json!({"lag": 0})),
+        ])
+    }
+
+    fn led_by_pg2() -> ClusterInfo {
+        cluster(vec![
+            member("pg1", "replica", json!({"lag": 0})),
+            member("pg2", "leader", json!({})),
+            member("pg3", "replica", json!({"lag": 0})),
+        ])
+    }
+
+    /// Follow the switchover from pg1 to pg2 through the cluster states of
+    /// `polls`, the last one repeating. Returns the final progress and the
+    /// step statuses seen before each poll.
+    fn follow_switchover(polls: Vec<ClusterInfo>, timeout: Duration) -> (VerificationProgress, Vec<Vec<StepStatus>>) {
+        let patroni_client = Patroni::new(vec![], &ClientOptions::default()).unwrap();
+        let cluster_service = ClusterService::new(patroni_client);
+        let before = led_by_pg1(json!({}), json!({"lag": 0}));
+        let plan = VerificationPlan::new(&cluster_service, &Action::Switchover, &before, Some(&before.members_map["pg2"]), false).unwrap();
+        let follower = Follower {
+            cluster_service,
+            progress: Arc::new(Mutex::new(VerificationProgress {
+                title: plan.title,
+                steps: plan
+                    .steps
+                    .iter()
+                    .map(|(description, _)| VerificationStep {
+                        description: description.clone(),
+                        status: StepStatus::Pending,
+                    })
+                    .collect(),
+                message: String::new(),
+                finished: false,
+                succeeded: false,
+            })),
+            max_lag_us: 1_000,
+            poll_interval: Duration::ZERO,
+            timeout,
+        };
+
+        let mut seen = vec![];
+        follower.run(plan.steps.into_iter().map(|(_, e)| e).collect(), || {
+            seen.push(follower.progress().steps.iter().map(|s| s.status).collect());
+            Ok(polls[(seen.len() - 1).min(polls.len() - 1)].clone())
+        });
+        (follower.progress(), seen)
+    }
+
+    fn statuses(progress: &VerificationProgress) -> Vec<StepStatus> {
+        progress.steps.iter().map(|s| s.status).collect()
+    }
+
+    #[test]
+    fn switchover_steps_are_reached_in_order() {
+        let (progress, seen) = follow_switchover(
+            vec![
+                led_by_pg1(json!({}), json!({"lag": 0})),
+                // pg2 leads, pg1 has not caught up yet
+                cluster(vec![
+                    member("pg1", "replica", json!({"state": "starting"})),
+                    member("pg2", "leader", json!({})),
+                ]),
+                led_by_pg2(),
+            ],
+            VERIFY_TIMEOUT,
+        );
+
+        assert_eq!(
+            seen,
+            [
+                vec![StepStatus::Pending, StepStatus::Pending],
+                vec![StepStatus::Running, StepStatus::Pending],
+                vec![StepStatus::Done, StepStatus::Running],
+            ]
+        );
+        assert_eq!(progress.steps[0].description, "pg2 leads the cluster");
+        assert_eq!(progress.steps[1].description, "pg1 streams as a replica");
+        assert_eq!(statuses(&progress), [StepStatus::Done, StepStatus::Done]);
+        assert!(progress.finished && progress.succeeded);
+        assert_eq!(progress.message, "Verified after 0s");
+    }
+
+    #[test]
+    fn steps_reached_between_polls_complete_together() {
+        let (progress, seen) = follow_switchover(vec![led_by_pg2()], VERIFY_TIMEOUT);
+
+        assert_eq!(seen.len(), 1);
+        assert_eq!(statuses(&progress), [StepStatus::Done, StepStatus::Done]);
+        assert!(progress.succeeded);
+    }
+
+    #[test]
+    fn failed_member_stops_following() {
+        let (progress, _) = follow_switchover(vec![led_by_pg1(json!({}), json!({"state": "crashed"}))], VERIFY_TIMEOUT);
+
+        assert_eq!(statuses(&progress), [StepStatus::Failed, StepStatus::Skipped]);
+        assert!(progress.finished && !progress.succeeded);
+        assert_eq!(progress.message, "Not verified: pg2 is in state crashed");
+    }
+
+    #[test]
+    fn another_leader_stops_following() {
+        let pg3_leads = cluster(vec![
+            member("pg1", "replica", json!({"lag": 0})),
+            member("pg2", "replica", json!({"lag": 0})),
+            member("pg3", "leader", json!({})),
+        ]);
+        let (progress, _) = follow_switchover(vec![pg3_leads], VERIFY_TIMEOUT);
+
+        assert_eq!(statuses(&progress), [StepStatus::Failed, StepStatus::Skipped]);
+        assert_eq!(progress.message, "Not verified: pg3 leads the cluster instead of pg2");
+    }
+
+    #[test]
+    fn timeout_fails_the_step_waited_for() {
+        let (progress, _) = follow_switchover(vec![led_by_pg1(json!({}), json!({"lag": 0}))], Duration::ZERO);
+
+        assert_eq!(statuses(&progress), [StepStatus::Failed, StepStatus::Skipped]);
+        assert!(progress.finished && !progress.succeeded);
+        assert_eq!(progress.message, "Not verified: timed out after 0s waiting for: pg2 leads the cluster");
+    }
+}
@@ -2,32 +2,12 @@ use std::process::Command;
 use std::io::{self, Read, Write};
 use std::os::unix::net::UnixStream;
 
-/// Execute a system command and return its output as a String
-pub fn exec_command(cmd: &str, args: &[&str]) -> io::Result<String> {
-    let output = Command::new(cmd)
-        .args(args)
-        .output()?;
-    
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Command failed with status {}: {}",
-                output.status,
-                String::from_utf8_lossy(&output.stderr)
-            ),
-        ))
-    }
-}
-
 /// Check if a systemd service is active
 pub fn is_service_active(service: &str) -> bool {
     Command::new("systemctl")
         .args(["is-active", &format!("{service}.service")])
         .output()
-        .map_or(false, |o| o.status.success())
+        .is_ok_and(|o| o.status.success())
 }
 
 /// Get service errors from journal
@@ -65,6 +45,33 @@ pub fn get_service_uptime(service: &str) -> String {
         .unwrap_or_else(|_| "unknown".into())
 }
 
+/// Get the seconds since the service last entered the active state
+pub fn get_service_uptime_secs(service: &str) -> Option<u64> {
+    let out = Command::new("systemctl")
+        .args([
+            "show",
+            &format!("{service}.service"),
+            "--property=ActiveEnterTimestampMonotonic",
+        ])
+        .output()
+        .ok()?;
+    let entered_us: u64 = String::from_utf8_lossy(&out.stdout)
+        .trim()
+        .split('=')
+        .nth(1)?
+        .parse()
+        .ok()?;
+    let since_boot: f64 = std::fs::read_to_string("/proc/uptime")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+
+    // Zero means the service was never started
+    (entered_us > 0).then(|| (since_boot - entered_us as f64 / 1_000_000.0).max(0.0) as u64)
+}
+
 /// Detect service version
 pub fn detect_service_version(service: &str) -> String {
     let try_cmd = |arg: &str| -> Option<String> {
@@ -124,30 +131,27 @@ pub fn detect_keepalived_vip() -> String {
     if let Ok(out) = Command::new("ip")
         .args(["-j", "-4", "addr", "show", "scope", "global"])
         .output()
+        && out.status.success()
+        && let Ok(ifaces) = serde_json::from_slice::<serde_json::Value>(&out.stdout)
+        && let Some(arr) = ifaces.as_array()
     {
-        if out.status.success() {
-            if let Ok(ifaces) = serde_json::from_slice::<serde_json::Value>(&out.stdout) {
-                if let Some(arr) = ifaces.as_array() {
-                    for iface in arr {
-                        // addr_info ‒ array with IP infos
-                        if let Some(addr_arr) = iface.get("addr_info").and_then(|v| v.as_array()) {
-                            for addr in addr_arr {
-                                let label = addr.get("label").and_then(|v| v.as_str()).unwrap_or("");
-                                let flags = addr.get("flags").and_then(|v| v.as_array());
-
-                                // Signs of VIP
-                                let is_secondary = flags.map_or(false, |f| {
-                                    f.iter().any(|fl| fl.as_str() == Some("secondary"))
-                                });
-                                let has_colon_in_label = label.contains(':');
-
-                                if is_secondary || has_colon_in_label {
-                                    if let Some(local) = addr.get("local").and_then(|v| v.as_str()) {
-                                        return local.to_string();
-                                    }
-                                }
-                            }
-                        }
+        for iface in arr {
+            // addr_info ‒ array with IP infos
+            if let Some(addr_arr) = iface.get("addr_info").and_then(|v| v.as_array()) {
+                for addr in addr_arr {
+                    let label = addr.get("label").and_then(|v| v.as_str()).unwrap_or("");
+                    let flags = addr.get("flags").and_then(|v| v.as_array());
+
+                    // Signs of VIP
+                    let is_secondary = flags.is_some_and(|f| {
+                        f.iter().any(|fl| fl.as_str() == Some("secondary"))
+                    });
+                    let has_colon_in_label = label.contains(':');
+
+                    if (is_secondary || has_colon_in_label)
+                        && let Some(local) = addr.get("local").and_then(|v| v.as_str())
+                    {
+                        return local.to_string();
                     }
                 }
             }
@@ -157,24 +161,78 @@ pub fn detect_keepalived_vip() -> String {
     if let Ok(out) = Command::new("ip")
         .args(["-o", "-4", "addr", "show", "scope", "global"])
         .output()
+        && out.status.success()
     {
-        if out.status.success() {
-            for line in String::from_utf8_lossy(&out.stdout).lines() {
-                let has_secondary = line.contains(" secondary ");
-                let has_label_colon = line.split_whitespace().any(|w| w.contains(':'));
-
-                if has_secondary || has_label_colon {
-                    if let Some(addr_field) = line.split_whitespace().nth(3) {
-                        return addr_field
-                            .split('/')
-                            .next()
-                            .unwrap_or("-")
-                            .to_string();
-                    }
-                }
+        for line in String::from_utf8_lossy(&out.stdout).lines() {
+            let has_secondary = line.contains(" secondary ");
+            let has_label_colon = line.split_whitespace().any(|w| w.contains(':'));
+
+            if (has_secondary || has_label_colon)
+                && let Some(addr_field) = line.split_whitespace().nth(3)
+            {
+                return addr_field
+                    .split('/')
+                    .next()
+                    .unwrap_or("-")
+                    .to_string();
             }
         }
     }
 
     "-".into()
-}
\ No newline at end of file
+}
+
+/// Whether `ip` is assigned to an interface of this host
+pub fn has_local_address(ip: &str) -> bool {
+    Command::new("ip")
+        .args(["-o", "addr", "show"])
+        .output()
+        .is_ok_and(|out| {
+            String::from_utf8_lossy(&out.stdout).lines().any(|line| {
+                line.split_whitespace()
+                    .nth(3)
+                    .and_then(|addr| addr.split('/').next())
+                    == Some(ip)
+            })
+        })
+}
+
+/// Count the distinct MAC addresses answering ARP requests for `ip`, using
+/// iputils `arping`. Replies from this host itself are not counted.
+pub fn count_arp_holders(ip: &str) -> io::Result<usize> {
+    let out = Command::new("arping").args(["-c", "3", "-w", "3", ip]).output()?;
+    // Exit code 1 only means that nobody answered
+    if !matches!(out.status.code(), Some(0 | 1)) {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let mut macs: Vec<&str> = vec![];
+    for line in stdout.lines().filter(|l| l.contains("reply from")) {
+        if let Some(mac) = line.split(['[', ']']).nth(1)
+            && !macs.contains(&mac)
+        {
+            macs.push(mac);
+        }
+    }
+
+    Ok(macs.len())
+}
+
+/// Name of the user running clup, from the environment or `id -un`
+pub fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .ok()
+        .or_else(|| {
+            Command::new("id")
+                .arg("-un")
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
@@ -49,6 +49,24 @@ pub fn create_cluster_layout(area: Rect) -> Rect {
     outer_block.inner(area)
 }
 
+/// Create the history layout
+pub fn create_history_layout(area: Rect) -> Rect {
+    let outer_block = ratatui::widgets::Block::default()
+        .title("Cluster History")
+        .borders(ratatui::widgets::Borders::ALL);
+
+    outer_block.inner(area)
+}
+
+/// Create the dynamic configuration layout
+pub fn create_config_layout(area: Rect) -> Rect {
+    let outer_block = ratatui::widgets::Block::default()
+        .title("Dynamic Configuration")
+        .borders(ratatui::widgets::Borders::ALL);
+
+    outer_block.inner(area)
+}
+
 /// Create the logs layout
 pub fn create_logs_layout(area: Rect) -> (Rect, Vec<Rect>) {
     let block = ratatui::widgets::Block::default()
@@ -1,73 +1,138 @@
 use crate::app::{App, Tab};
 use crate::config::Config;
 use crate::services::actions::ActionsService;
-use crate::services::cluster::ClusterService;
-use crate::services::logs::LogsService;
-use crate::services::overview::{OverviewData, OverviewService};
-use ratatui::backend::Backend;
+use crate::services::collector::{Collector, Snapshot};
+use crate::services::rolling_restart::RollingRestart;
+use crate::services::verification::Verification;
 use ratatui::Frame;
 
 mod layout;
 mod render;
 
-pub struct UI {
-    pub overview_service: OverviewService,
-    pub cluster_service: ClusterService,
-    pub logs_service: LogsService,
+/// A watched cluster with its own Patroni pollers and actions
+pub struct ClusterHandle {
+    /// Profile name, or `default` without `--clusters`
+    pub name: String,
+    /// Settings of the cluster's profile
+    pub config: Config,
+    pub collector: Collector,
     pub actions_service: ActionsService,
+    /// Last rolling restart started on this cluster
+    pub rolling_restart: Option<RollingRestart>,
+    /// Follow-up of the last other action sent to this cluster
+    pub verification: Option<Verification>,
+}
+
+pub struct UI {
+    /// Watched clusters; the first one also polls the local host
+    pub clusters: Vec<ClusterHandle>,
+    /// Index of the cluster shown in the Overview to Config tabs
+    pub current: usize,
     pub config: Config,
 }
 
 impl UI {
-    pub fn new(overview_service: OverviewService, cluster_service: ClusterService, logs_service: LogsService, config: Config) -> Self {
-        // Create actions service using the same Patroni client as cluster service
-        let actions_service = ActionsService::new(cluster_service.patroni_client.clone());
-
+    pub fn new(clusters: Vec<ClusterHandle>, config: Config) -> Self {
         UI {
-            overview_service,
-            cluster_service,
-            logs_service,
-            actions_service,
+            clusters,
+            current: 0,
             config,
         }
     }
 
-    pub fn draw_ui<B: Backend>(&self, frame: &mut Frame, app: &App) {
-        let chunks = layout::create_main_layout(frame.size());
+    pub fn cluster(&self) -> &ClusterHandle {
+        &self.clusters[self.current]
+    }
+
+    pub fn cluster_mut(&mut self) -> &mut ClusterHandle {
+        &mut self.clusters[self.current]
+    }
+
+    pub fn collector(&self) -> &Collector {
+        &self.cluster().collector
+    }
+
+    pub fn actions_service(&self) -> &ActionsService {
+        &self.cluster().actions_service
+    }
+
+    /// Whether more than one cluster is watched
+    pub fn is_multi_cluster(&self) -> bool {
+        self.clusters.len() > 1
+    }
+
+    /// Latest snapshot of the current cluster, with the local host data that
+    /// only the first cluster's collector polls
+    pub fn snapshot(&self) -> Snapshot {
+        let mut snapshot = self.collector().snapshot();
+        if self.current != 0 {
+            let host = self.clusters[0].collector.snapshot();
+            snapshot.overview = host.overview;
+            snapshot.logs = host.logs;
+        }
+
+        snapshot
+    }
+
+    /// Draw the current tab from the latest collected snapshot. Never blocks on I/O.
+    pub fn draw_ui(&self, frame: &mut Frame, app: &App) {
+        let chunks = layout::create_main_layout(frame.area());
+        let snapshot = self.snapshot();
 
         // Render tabs
-        let tabs = render::create_tabs(&app.current_tab);
+        let cluster_name = self.is_multi_cluster().then_some(self.cluster().name.as_str());
+        let tabs = render::create_tabs(&app.current_tab, cluster_name);
         frame.render_widget(tabs, chunks[0]);
 
         // Render content based on selected tab
         match app.current_tab {
-            Tab::Overview => self.draw_overview::<B>(frame, chunks[1]),
-            Tab::Cluster => self.draw_cluster::<B>(frame, chunks[1]),
-            Tab::Logs => self.draw_logs::<B>(
+            Tab::Overview => render::draw_overview(frame, chunks[1], &snapshot, &self.cluster().config),
+            Tab::Cluster => render::draw_cluster(
+                frame,
+                chunks[1],
+                &snapshot,
+                app.cluster_selected,
+                app.cluster_detail_open,
+                app.cluster_topology,
+                self.cluster().config.max_replication_lag_us(),
+            ),
+            Tab::Logs => self.draw_logs(
                 frame,
                 chunks[1],
+                &snapshot,
                 app.log_selected,
                 app.log_scroll,
                 app.log_focus_right,
             ),
-            Tab::Actions => self.draw_actions::<B>(frame, chunks[1], app),
+            Tab::Actions => self.draw_actions(frame, chunks[1], &snapshot, app),
+            Tab::History => render::draw_history(frame, chunks[1], &snapshot),
+            Tab::Config => render::draw_config(
+                frame,
+                chunks[1],
+                &snapshot,
+                app.config_selected,
+                &app.config_collapsed,
+                app.config_edit.as_ref(),
+                app.action_pending.as_ref().map(|p| &p.request),
+                app.config_result.as_ref(),
+            ),
+            Tab::Audit => render::draw_audit(
+                frame,
+                chunks[1],
+                &app.audit_records,
+                app.audit_selected,
+                self.cluster().config.audit_log_path().as_deref(),
+                app.audit_error.as_deref(),
+            ),
+            Tab::Clusters => render::draw_clusters(frame, chunks[1], &self.clusters, app.clusters_selected, self.current),
         }
     }
 
-    fn draw_overview<B: Backend>(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
-        let data: OverviewData = self.overview_service.get_overview();
-        render::draw_overview(frame, area, &data, &self.overview_service, &self.config);
-    }
-
-    fn draw_cluster<B: Backend>(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
-        let data = self.cluster_service.get_cluster_info();
-        render::draw_cluster(frame, area, &data);
-    }
-
-    fn draw_logs<B: Backend>(
+    fn draw_logs(
         &self,
         frame: &mut Frame,
         area: ratatui::layout::Rect,
+        snapshot: &Snapshot,
         selected: usize,
         scroll: u16,
         focus_right: bool,
@@ -78,23 +143,19 @@ impl UI {
         } else {
             "unknown"
         };
-        let lines = self.logs_service.read_logs(selected_service, 100);
+        let logs = snapshot.logs.get(selected_service);
 
-        render::draw_logs(frame, area, &services, selected, scroll, focus_right, &lines, selected_service);
+        render::draw_logs(frame, area, &services, selected, scroll, focus_right, logs, selected_service);
     }
 
-    fn draw_actions<B: Backend>(&self, frame: &mut Frame, area: ratatui::layout::Rect, app: &App) {
-        let cluster_info = self.actions_service.get_cluster_info();
+    fn draw_actions(&self, frame: &mut Frame, area: ratatui::layout::Rect, snapshot: &Snapshot, app: &App) {
         render::draw_actions(
-            frame, 
-            area, 
-            &self.actions_service,
-            app.action_selected,
-            app.action_confirmation,
-            app.action_confirmation_yes,
-            &app.action_target_node,
-            &app.action_error,
-            &cluster_info
+            frame,
+            area,
+            app,
+            snapshot.cluster.as_ref(),
+            snapshot.dynamic_config.as_ref(),
+            self.cluster().config.max_replication_lag_us(),
         );
     }
 }
@@ -1,19 +1,49 @@
-use crate::app::Tab;
+use crate::app::{ActionField, App, ConfigEdit, PendingRequest, Tab};
 use crate::config::Config;
-use crate::patroni::patroni::ClusterInfo;
-use crate::services::actions::{Action, ActionsService};
-use crate::services::overview::{OverviewData, OverviewService};
-use crate::system;
-use crate::ui::layout;
+use crate::patroni::error::PatroniError;
+use crate::patroni::patroni::{ClusterInfo, NodeStatus};
+use crate::services::actions::Action;
+use crate::services::audit::{AuditClusterState, AuditRecord};
+use crate::services::collector::{MemberDetail, Sample, Snapshot};
+use crate::services::dynamic_config::{config_patch, display_value, flatten_config};
+use crate::services::overview::OverviewData;
+use crate::services::preflight::{self, CheckStatus, PreflightCheck};
+use crate::services::rolling_restart::{RollingRestart, StepStatus};
+use crate::services::topology::{self, SyncMode, SyncSettings};
+use crate::services::verification::VerificationProgress;
+use crate::ui::{ClusterHandle, layout};
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Rect};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, Tabs, Wrap};
+use ratatui::widgets::{
+    Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Tabs, Wrap,
+};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Create the tabs widget; with several clusters the dashboard tab is added
+/// and the title names the cluster shown in the other tabs
+pub fn create_tabs(current_tab: &Tab, cluster_name: Option<&str>) -> Tabs<'static> {
+    let mut tab_titles = vec![
+        "1: Overview",
+        "2: Cluster",
+        "3: Logs",
+        "4: Actions",
+        "5: History",
+        "6: Config",
+        "7: Audit",
+    ];
+    if cluster_name.is_some() {
+        tab_titles.push("0: Clusters");
+    }
+    let title = match cluster_name {
+        Some(name) => format!("Navigation (cluster {name})"),
+        None => "Navigation".to_string(),
+    };
 
-/// Create the tabs widget
-pub fn create_tabs(current_tab: &Tab) -> Tabs<'static> {
-    let tab_titles = ["1: Overview", "2: Cluster", "3: Logs", "4: Actions"];
     Tabs::new(
         tab_titles
             .iter()
@@ -21,91 +51,156 @@ pub fn create_tabs(current_tab: &Tab) -> Tabs<'static> {
             .map(Line::from)
             .collect::<Vec<Line>>(),
     )
-    .block(Block::default().borders(Borders::ALL).title("Navigation"))
+    .block(Block::default().borders(Borders::ALL).title(title))
     .highlight_style(Style::default().fg(Color::Yellow))
     .select(*current_tab as usize)
 }
 
+/// Describe how old a collected sample is
+fn age_label<T>(sample: Option<&Sample<T>>) -> String {
+    match sample {
+        Some(sample) => format!("updated {:.1}s ago", sample.age().as_secs_f64()),
+        None => "loading...".to_string(),
+    }
+}
+
+/// Describe when a source was last fetched successfully
+fn last_success_label<T>(sample: Option<&Sample<T>>) -> String {
+    match sample {
+        Some(sample) => format!("{:.1}s ago", sample.age().as_secs_f64()),
+        None => "never".to_string(),
+    }
+}
+
+/// Build the error banner shown when fetching a source fails
+fn error_banner<'a, T>(
+    source: &str,
+    error: &'a Sample<PatroniError>,
+    last_ok: Option<&Sample<T>>,
+) -> Paragraph<'a> {
+    Paragraph::new(vec![
+        Line::from(vec![
+            Span::styled(
+                format!("{source}: {}", error.value.kind()),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!(
+                "   failed {:.1}s ago, last success {}",
+                error.age().as_secs_f64(),
+                last_success_label(last_ok),
+            )),
+        ]),
+        Line::from(error.value.to_string()),
+    ])
+    .block(
+        Block::default()
+            .title("Error")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red)),
+    )
+    .wrap(Wrap { trim: true })
+}
+
 /// Draw the overview tab
 pub fn draw_overview(
     frame: &mut Frame,
     area: Rect,
-    data: &OverviewData,
-    overview_service: &OverviewService,
+    snapshot: &Snapshot,
     config: &Config,
 ) {
     let (outer_area, chunks) = layout::create_overview_layout(area);
 
     // Render the outer block
     let outer_block = Block::default()
-        .title("Overview")
+        .title(format!(
+            "Overview (cluster {}, services {})",
+            age_label(snapshot.cluster.as_ref()),
+            age_label(snapshot.overview.as_ref()),
+        ))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::White));
     frame.render_widget(outer_block, outer_area);
 
+    let overview = snapshot.overview.as_ref().map(|s| &s.value);
+
     // Create and render the header
     let header_chunks = layout::create_overview_header_layout(chunks[0]);
-    draw_overview_header(frame, data, header_chunks[0], header_chunks[1], overview_service, config);
+    draw_overview_header(frame, snapshot, overview, header_chunks[0], header_chunks[1], config);
 
     // Create and render the table
-    draw_overview_table(frame, data, chunks[1]);
+    draw_overview_table(frame, overview, chunks[1]);
 }
 
 /// Draw the overview header
 fn draw_overview_header(
     frame: &mut Frame,
-    data: &OverviewData,
+    snapshot: &Snapshot,
+    overview: Option<&OverviewData>,
     header_area: Rect,
     subheader_area: Rect,
-    overview_service: &OverviewService,
     config: &Config,
 ) {
-    let role_raw = data
-        .cluster_data
-        .members_map
-        .get(&data.cluster_data.node_name)
+    let cluster = snapshot.cluster.as_ref().map(|s| &s.value);
+    let local_node = cluster.and_then(|c| c.members_map.get(&c.node_name));
+    let role_raw = local_node
         .map(|n| n.role.as_str())
+        .or(cluster.map(|c| c.patroni_data.role.as_str()))
         .unwrap_or("-");
     let role_human = match role_raw {
-        "leader" => "Primary",
+        "leader" | "master" | "primary" => "Primary",
+        "-" => "-",
         _ => "Replica",
     };
 
-    let title = Line::from(vec![
+    let mut title = Line::from(vec![
         Span::styled(
-            format!("Cluster: {}  ", data.cluster_data.scope),
+            format!("Cluster: {}  ", cluster.map_or("-", |c| c.scope.as_str())),
             Style::default()
                 .add_modifier(Modifier::BOLD)
                 .fg(Color::Cyan),
         ),
         Span::raw(format!(
-            "Node: {}   Role: {}   Leader: {}",
-            data.cluster_data.node_name, role_human, data.cluster_data.leader_node_name
+            "Node: {}   Role: {}   State: {}   Leader: {}",
+            cluster.map_or("-", |c| c.node_name.as_str()),
+            role_human,
+            cluster.map_or("-", |c| c.patroni_data.state.as_str()),
+            cluster.and_then(|c| c.leader_node_name.as_deref()).unwrap_or("-"),
         )),
     ]);
 
+    // Patroni REST endpoint in use, highlighted when failed over from the configured one
+    if let Some(c) = cluster {
+        title.spans.push(Span::raw("   API: "));
+        title.spans.push(Span::styled(
+            if c.api_fallback {
+                format!("{} (fallback)", c.api_endpoint)
+            } else {
+                c.api_endpoint.clone()
+            },
+            Style::default().fg(if c.api_fallback { Color::Yellow } else { Color::Green }),
+        ));
+    }
+
     let title_par = Paragraph::new(title)
         .block(Block::default().borders(Borders::NONE));
     frame.render_widget(title_par, header_area);
 
-    let lag_str = data
-        .cluster_data
-        .members_map
-        .get(&data.cluster_data.node_name)
+    let lag_str = local_node
         .and_then(|n| n.lag)
         .map(|micros| format!("{:.1}s", micros as f64 / 1_000_000.0))
         .unwrap_or_else(|| "-".to_string());
 
     // HAProxy backend stats
-    let (ha_curr, ha_max) = overview_service.fetch_haproxy_backend_stats();
+    let (ha_curr, ha_max) = overview.map_or((0, 0), |o| o.haproxy_backends);
 
     // Replication health
-    let repl_ok = data.cluster_data.replication_ok(config.max_replication_lag_us());
+    let repl_ok = cluster.is_some_and(|c| c.replication_ok(config.max_replication_lag_us()));
 
-    // VIP
-    let vip = system::detect_keepalived_vip();
+    // VIP and host
+    let vip = overview.map_or("-", |o| o.vip.as_str());
+    let host = overview.map_or("-".to_string(), |o| format!("{} ({})", o.hostname, o.ip));
 
-    let sub_lines = vec![
+    let mut sub_lines = vec![
         Line::from(vec![
             Span::styled("Lag: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(format!("{lag_str}   ")),
@@ -118,10 +213,36 @@ fn draw_overview_header(
             Span::styled(" HAProxy: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(format!("Backend OK ({}/{})   ", ha_curr, ha_max)),
             Span::styled("VIP: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(vip),
+            Span::raw(format!("{vip}   ")),
+            Span::styled("Host: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(host),
         ]),
     ];
 
+    if let Some(error) = &snapshot.cluster_error {
+        sub_lines.push(Line::from(Span::styled(
+            format!(
+                " Patroni {}: {} (last success {})",
+                error.value.kind(),
+                error.value,
+                last_success_label(snapshot.cluster.as_ref()),
+            ),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    if let Some(error) = &snapshot.alert_error {
+        sub_lines.push(Line::from(Span::styled(
+            format!(" Alert delivery failed {}: {}", last_success_label(Some(error)), error.value),
+            Style::default().fg(Color::Red),
+        )));
+    } else if let Some(alert) = &snapshot.last_alert {
+        sub_lines.push(Line::from(Span::styled(
+            format!(" Alert {}: {}", last_success_label(Some(alert)), alert.value.message),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+
     let sub_par = Paragraph::new(sub_lines)
         .block(Block::default().borders(Borders::NONE))
         .wrap(Wrap { trim: true });
@@ -132,11 +253,11 @@ fn draw_overview_header(
 /// Draw the overview table
 fn draw_overview_table(
     frame: &mut Frame,
-    data: &OverviewData,
+    overview: Option<&OverviewData>,
     table_area: Rect,
 ) {
-    let rows: Vec<Row> = data
-        .components
+    let rows: Vec<Row> = overview
+        .map_or(&[][..], |o| &o.components)
         .iter()
         .map(|c| {
             let status_text = if c.up { "UP" } else { "DOWN" };
@@ -179,20 +300,230 @@ fn draw_overview_table(
 pub fn draw_cluster(
     frame: &mut Frame,
     area: Rect,
-    data: &ClusterInfo,
+    snapshot: &Snapshot,
+    selected: usize,
+    detail_open: bool,
+    topology: bool,
+    max_lag_us: u64,
 ) {
     let inner_area = layout::create_cluster_layout(area);
 
     // Render the outer block
     let outer_block = Block::default()
-        .title("Cluster Status")
+        .title(format!(
+            "Cluster Status (API {}, {})",
+            snapshot.cluster.as_ref().map_or("-", |s| s.value.api_endpoint.as_str()),
+            age_label(snapshot.cluster.as_ref()),
+        ))
         .borders(Borders::ALL);
     frame.render_widget(outer_block, area);
 
-    let rows: Vec<Row> = data
-        .members
+    // Keep showing the last known members below the error banner
+    let inner_area = match &snapshot.cluster_error {
+        Some(error) => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(4), Constraint::Min(0)])
+                .split(inner_area);
+            frame.render_widget(error_banner("Patroni", error, snapshot.cluster.as_ref()), chunks[0]);
+            chunks[1]
+        }
+        None => inner_area,
+    };
+
+    let members = snapshot.cluster.as_ref().map_or(&[][..], |s| &s.value.members);
+
+    // Members needing attention: pending or scheduled restarts and failover-relevant tags
+    let notes = member_notes(members);
+    let detail = if detail_open {
+        Some(member_detail_lines(snapshot.member_detail.as_ref()))
+    } else {
+        None
+    };
+
+    let mut constraints = vec![Constraint::Min(5)];
+    if !notes.is_empty() {
+        constraints.push(Constraint::Length(notes.len() as u16 + 2));
+    }
+    if let Some(lines) = &detail {
+        constraints.push(Constraint::Length(lines.len() as u16 + 2));
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner_area);
+    if topology {
+        let lines = topology_lines(snapshot, selected, max_lag_us);
+        let topology = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Topology (j/k: select, Enter: details, Esc: close, t: table)"),
+        );
+        frame.render_widget(topology, chunks[0]);
+    } else {
+        frame.render_widget(members_table(members, selected), chunks[0]);
+    }
+
+    if !notes.is_empty() {
+        let notes_par = Paragraph::new(notes)
+            .block(Block::default().borders(Borders::ALL).title("Attention"))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(notes_par, chunks[1]);
+    }
+
+    if let Some(lines) = detail {
+        let title = match &snapshot.member_detail {
+            Some(detail) => format!(
+                "Node {} ({}, {})",
+                detail.value.name,
+                detail.value.api_url.as_deref().unwrap_or("no api_url"),
+                age_label(Some(detail)),
+            ),
+            None => "Node".to_string(),
+        };
+        let detail_par = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(title),
+            )
+            .wrap(Wrap { trim: true });
+        frame.render_widget(detail_par, chunks[chunks.len() - 1]);
+    }
+}
+
+/// Describe the status a member reports on its own `/patroni` endpoint
+fn member_detail_lines(detail: Option<&Sample<MemberDetail>>) -> Vec<Line<'static>> {
+    let Some(detail) = detail else {
+        return vec![Line::from("loading...")];
+    };
+    let status = match &detail.value.status {
+        Ok(status) => status,
+        Err(e) => {
+            return vec![Line::from(Span::styled(
+                format!("{}: {}", e.kind(), e),
+                Style::default().fg(Color::Red),
+            ))];
+        }
+    };
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let field = |name: &str, value: String| {
+        Line::from(vec![Span::styled(format!("{name}: "), bold), Span::raw(value)])
+    };
+
+    let xlog = &status.xlog;
+    let wal = match (xlog.location, xlog.received_location, xlog.replayed_location) {
+        (Some(location), _, _) => format!("location {}", format_lsn(location)),
+        (None, received, replayed) => format!(
+            "received {}, replayed {}",
+            received.map_or("-".to_string(), format_lsn),
+            replayed.map_or("-".to_string(), format_lsn),
+        ),
+    };
+    let dcs_last_seen = status.dcs_last_seen.map_or("-".to_string(), |seen| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        format!("{}s ago", now.saturating_sub(seen))
+    });
+    let tags = status.tags.labels();
+    let pending = if status.pending_restart {
+        let reasons = pending_restart_reasons(&status.pending_restart_reason);
+        if reasons.is_empty() {
+            "yes".to_string()
+        } else {
+            format!("yes ({})", reasons.join(", "))
+        }
+    } else {
+        "no".to_string()
+    };
+
+    vec![
+        field(
+            "State",
+            format!(
+                "{} ({})",
+                status.state.as_deref().unwrap_or("-"),
+                status.role.as_deref().unwrap_or("-"),
+            ),
+        ),
+        field(
+            "Versions",
+            format!(
+                "PostgreSQL {}, Patroni {}",
+                status.server_version.map_or("-".to_string(), format_server_version),
+                status
+                    .patroni
+                    .as_ref()
+                    .and_then(|p| p.version.as_deref())
+                    .unwrap_or("-"),
+            ),
+        ),
+        field(
+            "Postmaster start",
+            status.postmaster_start_time.clone().unwrap_or_else(|| "-".to_string()),
+        ),
+        field("Timeline", optional_label(status.timeline)),
+        field("WAL", wal),
+        field(
+            "Replication",
+            format!(
+                "{}, last replayed at {}, replay {}",
+                status.replication_state.as_deref().unwrap_or("-"),
+                xlog.replayed_timestamp.as_deref().unwrap_or("-"),
+                if xlog.paused { "paused" } else { "running" },
+            ),
+        ),
+        field(
+            "Cluster paused",
+            if status.pause { "yes" } else { "no" }.to_string(),
+        ),
+        field("DCS last seen", dcs_last_seen),
+        field("Tags", if tags.is_empty() { "-".to_string() } else { tags.join(", ") }),
+        field("Pending restart", pending),
+    ]
+}
+
+/// Format a WAL position as PostgreSQL LSN, e.g. `0/5000060`
+fn format_lsn(location: u64) -> String {
+    format!("{:X}/{:X}", location >> 32, location & 0xFFFF_FFFF)
+}
+
+/// Format a `server_version` number, e.g. `160002` as `16.2` or `90624` as `9.6.24`
+fn format_server_version(version: u64) -> String {
+    if version >= 100_000 {
+        format!("{}.{}", version / 10_000, version % 10_000)
+    } else {
+        format!("{}.{}.{}", version / 10_000, version / 100 % 100, version % 100)
+    }
+}
+
+/// Describe parameters awaiting a restart, e.g. `shared_buffers: 128MB -> 256MB`
+fn pending_restart_reasons(reasons: &BTreeMap<String, Value>) -> Vec<String> {
+    reasons
         .iter()
-        .map(|node| {
+        .map(|(param, change)| {
+            match (change["old_value"].as_str(), change["new_value"].as_str()) {
+                (Some(old), Some(new)) => format!("{param}: {old} -> {new}"),
+                _ => param.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Format an optional value, using `-` when it is missing
+fn optional_label<T: ToString>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |v| v.to_string())
+}
+
+/// Members as a table, one row each
+fn members_table(members: &[NodeStatus], selected: usize) -> Table<'static> {
+    let rows: Vec<Row> = members
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
             let color = match node.role.as_str() {
                 "leader" => Color::Green,
                 "replica" => Color::Cyan,
@@ -205,37 +536,716 @@ pub fn draw_cluster(
                 Color::Cyan
             };
 
+            let (restart, restart_color) = if node.pending_restart {
+                ("pending", Color::Yellow)
+            } else if node.scheduled_restart.is_some() {
+                ("scheduled", Color::Cyan)
+            } else {
+                ("-", Color::Reset)
+            };
+
+            let tags = node.tags.labels();
+            let tags_color = if node.tags.nofailover { Color::Yellow } else { Color::Reset };
+
             Row::new(vec![
                 Cell::from(node.name.clone()),
                 Cell::from(node.role.clone()).style(Style::default().fg(color)),
                 Cell::from(node.state.clone()).style(Style::default().fg(status_color)),
                 Cell::from(node.host.clone()),
-                Cell::from(node.lag.map_or("-".to_string(), |l| l.to_string())),
+                Cell::from(optional_label(node.port)),
+                Cell::from(optional_label(node.timeline)),
+                Cell::from(optional_label(node.lag)),
+                Cell::from(optional_label(node.receive_lag)),
+                Cell::from(optional_label(node.replay_lag)),
+                Cell::from(
+                    node.lsn
+                        .clone()
+                        .or_else(|| node.replay_lsn.clone())
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+                Cell::from(optional_label(node.receive_lsn.as_deref())),
+                Cell::from(restart).style(Style::default().fg(restart_color)),
+                Cell::from(if tags.is_empty() { "-".to_string() } else { tags.join(",") })
+                    .style(Style::default().fg(tags_color)),
             ])
+            .style(if i == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            })
         })
         .collect();
 
-    let table = Table::new(rows, &[Constraint::Length(15), Constraint::Length(8)])
-        .block(Block::default().borders(Borders::ALL).title("Nodes"))
-        .widths(&[
-            Constraint::Length(10),
+    Table::new(rows, [Constraint::Length(15), Constraint::Length(8)])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Nodes (j/k: select, Enter: details, Esc: close, t: topology)"),
+        )
+        .widths([
             Constraint::Length(10),
+            Constraint::Length(13),
             Constraint::Length(10),
             Constraint::Length(16),
             Constraint::Length(6),
+            Constraint::Length(4),
+            Constraint::Length(10),
+            Constraint::Length(11),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(9),
+            Constraint::Min(10),
         ])
         .header(
-            Row::new(["Name", "Role", "State", "Host", "Lag"]).style(
+            Row::new([
+                "Name", "Role", "State", "Host", "Port", "TL", "Lag", "Receive lag",
+                "Replay lag", "LSN", "Receive LSN", "Restart", "Tags",
+            ])
+            .style(
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             ),
-        );
+        )
+}
 
-    frame.render_widget(table, inner_area);
+/// The replication tree below the synchronous replication settings
+fn topology_lines(snapshot: &Snapshot, selected: usize, max_lag_us: u64) -> Vec<Line<'static>> {
+    let Some(cluster) = snapshot.cluster.as_ref().map(|s| &s.value) else {
+        return vec![Line::from("Cluster state is not available yet")];
+    };
+    let entries = topology::build(cluster);
+    let sync_standbys: Vec<&str> = entries
+        .iter()
+        .filter(|e| topology::is_sync_standby(&e.node))
+        .map(|e| e.node.name.as_str())
+        .collect();
+
+    let mut lines = vec![];
+    match snapshot.dynamic_config.as_ref().map(|s| SyncSettings::from_config(&s.value)) {
+        Some(sync) => {
+            let mut label = format!("Synchronous mode: {}", sync.mode.as_str());
+            if sync.mode != SyncMode::Off {
+                label.push_str(&format!(
+                    " ({}strict, synchronous_node_count {}, sync standbys: {})",
+                    if sync.strict { "" } else { "not " },
+                    sync.node_count,
+                    if sync_standbys.is_empty() { "none".to_string() } else { sync_standbys.join(", ") }
+                ));
+            }
+            lines.push(Line::from(label));
+
+            if sync.mode != SyncMode::Off && (sync_standbys.len() as u64) < sync.node_count {
+                lines.push(if sync.strict {
+                    Line::styled(
+                        "Too few synchronous standbys: commits wait until one is available",
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Line::styled(
+                        "Too few synchronous standbys: commits do not wait for all of them",
+                        Style::default().fg(Color::Yellow),
+                    )
+                });
+            }
+        }
+        None => lines.push(Line::styled(
+            "Synchronous mode: unknown (/config not available)",
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+    lines.push(Line::from(""));
+
+    if cluster.leader().is_none() {
+        lines.push(Line::styled(
+            "No leader: members are shown without an upstream",
+            Style::default().fg(Color::Red),
+        ));
+    }
+
+    for (i, entry) in entries.iter().enumerate() {
+        let node = &entry.node;
+
+        // Guides of the levels above, then the branch to this member
+        let mut prefix = String::new();
+        if let Some((last, above)) = entry.last_at_level.split_last() {
+            for ancestor_last in above {
+                prefix.push_str(if *ancestor_last { "    " } else { "│   " });
+            }
+            prefix.push_str(if *last { "└── " } else { "├── " });
+        }
+
+        let (role, role_color) = match node.role.as_str() {
+            "leader" | "master" | "primary" => ("leader", Color::Green),
+            "standby_leader" => ("standby leader", Color::Green),
+            "sync_standby" => ("sync standby", Color::Cyan),
+            "quorum_standby" => ("quorum standby", Color::Cyan),
+            "replica" => ("async replica", Color::Reset),
+            other => (other, Color::Yellow),
+        };
+        let state_color = match node.state.as_str() {
+            "running" | "streaming" => Color::Green,
+            _ => Color::Yellow,
+        };
+
+        let mut spans = vec![
+            Span::raw(prefix),
+            Span::styled(node.name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("  "),
+            Span::styled(role.to_string(), Style::default().fg(role_color)),
+            Span::raw("  "),
+            Span::styled(node.state.clone(), Style::default().fg(state_color)),
+            Span::raw(format!("  TL {}", optional_label(node.timeline))),
+        ];
+        if entry.depth() > 0 {
+            spans.push(match node.lag {
+                Some(lag) if lag > max_lag_us => Span::styled(format!("  lag {lag}"), Style::default().fg(Color::Red)),
+                Some(lag) => Span::raw(format!("  lag {lag}")),
+                None => Span::styled("  lag unknown", Style::default().fg(Color::Yellow)),
+            });
+        }
+        if let Some(note) = &entry.note {
+            spans.push(Span::styled(format!("  ({note})"), Style::default().fg(Color::Yellow)));
+        }
+
+        let mut line = Line::from(spans);
+        if i == selected {
+            line = line.style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Describe members with pending or scheduled restarts and failover-relevant tags
+fn member_notes(members: &[NodeStatus]) -> Vec<Line<'static>> {
+    let mut notes = vec![];
+
+    for node in members {
+        if node.pending_restart {
+            let reasons = pending_restart_reasons(&node.pending_restart_reason);
+            let reasons = if reasons.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", reasons.join(", "))
+            };
+            notes.push(Line::from(Span::styled(
+                format!("{}: pending restart{}", node.name, reasons),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+
+        if let Some(scheduled) = &node.scheduled_restart {
+            let version = scheduled
+                .postgres_version
+                .as_ref()
+                .map_or(String::new(), |v| format!(" if version < {v}"));
+            notes.push(Line::from(format!(
+                "{}: restart scheduled at {}{}",
+                node.name, scheduled.schedule, version
+            )));
+        }
+
+        if node.tags.nofailover {
+            notes.push(Line::from(format!("{}: tagged nofailover, never promoted", node.name)));
+        }
+        if node.tags.nosync {
+            notes.push(Line::from(format!("{}: tagged nosync, never a sync standby", node.name)));
+        }
+    }
+
+    notes
+}
+
+/// Draw the history tab
+pub fn draw_history(
+    frame: &mut Frame,
+    area: Rect,
+    snapshot: &Snapshot,
+) {
+    let inner_area = layout::create_history_layout(area);
+
+    // Render the outer block
+    let outer_block = Block::default()
+        .title(format!("Cluster History ({})", age_label(snapshot.history.as_ref())))
+        .borders(Borders::ALL);
+    frame.render_widget(outer_block, area);
+
+    let history = snapshot.history.as_ref().map_or(&[][..], |s| &s.value);
+
+    // Error banner or a summary of the last leader change above the table
+    let summary = match &snapshot.history_error {
+        Some(error) => error_banner("Patroni /history", error, snapshot.history.as_ref()),
+        None => {
+            let text = match history.first() {
+                Some(last) => Line::from(vec![
+                    Span::styled("Last leader change: ", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(
+                        "{} to {} ({})",
+                        last.timestamp.as_deref().unwrap_or("unknown time"),
+                        last.new_leader.as_deref().unwrap_or("unknown leader"),
+                        last.reason,
+                    )),
+                ]),
+                None => Line::from("No timeline switches recorded"),
+            };
+            Paragraph::new(text).block(Block::default().borders(Borders::ALL))
+        }
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(4), Constraint::Min(0)])
+        .split(inner_area);
+    frame.render_widget(summary, chunks[0]);
+
+    let rows: Vec<Row> = history
+        .iter()
+        .map(|entry| {
+            Row::new(vec![
+                Cell::from(entry.timeline.to_string()),
+                Cell::from(format_lsn(entry.lsn)),
+                Cell::from(entry.timestamp.clone().unwrap_or_else(|| "-".to_string())),
+                Cell::from(entry.new_leader.clone().unwrap_or_else(|| "-".to_string()))
+                    .style(Style::default().fg(Color::Green)),
+                Cell::from(entry.reason.clone()),
+            ])
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Length(14),
+            Constraint::Length(33),
+            Constraint::Length(14),
+            Constraint::Min(20),
+        ],
+    )
+    .block(Block::default().borders(Borders::ALL).title("Timeline switches (newest first)"))
+    .header(
+        Row::new(["Timeline", "End LSN", "Time", "New leader", "Reason"]).style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+    );
+
+    frame.render_widget(table, chunks[1]);
+}
+
+/// Results of the pre-flight checks for the confirmation dialog, with the
+/// override toggle when one of them failed
+fn preflight_lines(checks: Option<&[PreflightCheck]>, override_checks: bool) -> Vec<Line<'static>> {
+    let Some(checks) = checks else {
+        return vec![Line::styled("Running pre-flight checks...", Style::default().fg(Color::DarkGray))];
+    };
+
+    let mut lines: Vec<Line> = checks
+        .iter()
+        .map(|check| {
+            let color = match check.status {
+                CheckStatus::Pass => Color::Green,
+                CheckStatus::Warn => Color::Yellow,
+                CheckStatus::Fail => Color::Red,
+            };
+            Line::from(vec![
+                Span::styled(format!("[{}] ", check.status.as_str()), Style::default().fg(color)),
+                Span::raw(format!("{}: {}", check.name, check.detail)),
+            ])
+            .left_aligned()
+        })
+        .collect();
+
+    if preflight::has_failures(checks) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("o", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!(": Override failed checks: {}", checkbox(override_checks))),
+        ]));
+    }
+
+    lines
+}
+
+/// Draw the audit log: one row per action, newest first, with the details
+/// of the selected one below
+pub fn draw_audit(
+    frame: &mut Frame,
+    area: Rect,
+    records: &[AuditRecord],
+    selected: usize,
+    path: Option<&Path>,
+    error: Option<&str>,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(6), Constraint::Length(12)])
+        .split(area);
+
+    let rows: Vec<Row> = records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let user = match &record.sudo_user {
+                Some(sudo_user) => format!("{sudo_user} as {}@{}", record.user, record.hostname),
+                None => format!("{}@{}", record.user, record.hostname),
+            };
+            let row = Row::new(vec![
+                Cell::from(record.time.clone()),
+                Cell::from(user),
+                Cell::from(record.cluster.clone()),
+                Cell::from(record.action.clone()),
+                Cell::from(record.target.clone().unwrap_or_else(|| "-".to_string())),
+                audit_result_cell(record),
+            ]);
+
+            if i == selected {
+                row.style(Style::default().bg(Color::DarkGray))
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let title = format!(
+        "Audit log {} (Up/Down to select, r to reload)",
+        path.map_or("-".to_string(), |p| p.display().to_string())
+    );
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(21),
+            Constraint::Length(24),
+            Constraint::Length(12),
+            Constraint::Length(18),
+            Constraint::Length(12),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(["Time (UTC)", "User", "Cluster", "Action", "Target", "Result"])
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().title(title).borders(Borders::ALL));
+    frame.render_widget(table, chunks[0]);
+
+    let lines = match (error, records.get(selected)) {
+        (Some(error), _) => vec![Line::from(Span::styled(error.to_string(), Style::default().fg(Color::Red)))],
+        (None, Some(record)) => audit_detail_lines(record),
+        (None, None) => vec![Line::from("No actions recorded yet")],
+    };
+    let detail = Paragraph::new(lines)
+        .block(Block::default().title("Details").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(detail, chunks[1]);
+}
+
+fn audit_result_cell(record: &AuditRecord) -> Cell<'static> {
+    match (&record.error, record.status) {
+        (None, Some(status)) => Cell::from(format!("OK ({status})")).style(Style::default().fg(Color::Green)),
+        (None, None) => Cell::from("OK").style(Style::default().fg(Color::Green)),
+        (Some(_), Some(status)) => Cell::from(format!("HTTP {status}")).style(Style::default().fg(Color::Red)),
+        (Some(_), None) => Cell::from("no response").style(Style::default().fg(Color::Red)),
+    }
+}
+
+fn audit_detail_lines(record: &AuditRecord) -> Vec<Line<'static>> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(vec![
+        Span::styled("Request: ", bold),
+        Span::raw(format!(
+            "{} {} {}",
+            record.method,
+            record.url,
+            record.request.as_ref().map_or(String::new(), |body| body.to_string())
+        )),
+    ])];
+    if let Some(error) = &record.error {
+        lines.push(Line::from(vec![
+            Span::styled("Error: ", bold),
+            Span::styled(error.clone(), Style::default().fg(Color::Red)),
+        ]));
+    }
+
+    for (label, state) in [("Before: ", Some(&record.before)), ("After:  ", record.after.as_ref())] {
+        let text = match state {
+            None => "not recorded".to_string(),
+            Some(AuditClusterState { error: Some(error), .. }) => format!("unavailable ({error})"),
+            Some(state) => {
+                let members: Vec<String> = state
+                    .members
+                    .iter()
+                    .map(|m| format!("{} {} {}", m.name, m.role, m.state))
+                    .collect();
+                format!(
+                    "leader {}{}; {}",
+                    // Records written before the fix stored `-` for no leader
+                    if matches!(state.leader.as_str(), "" | "-") { "none" } else { &state.leader },
+                    if state.paused { ", paused" } else { "" },
+                    members.join(", ")
+                )
+            }
+        };
+        lines.push(Line::from(vec![Span::styled(label, bold), Span::raw(text)]));
+    }
+
+    lines
+}
+
+/// Health of a watched cluster as shown on the dashboard
+fn cluster_health(snapshot: &Snapshot, max_lag_us: u64) -> (&'static str, Color) {
+    match (&snapshot.cluster, &snapshot.cluster_error) {
+        (_, Some(_)) => ("UNREACHABLE", Color::Red),
+        (None, None) => ("loading", Color::DarkGray),
+        (Some(c), None) if !c.value.has_healthy_leader() => ("NO LEADER", Color::Red),
+        (Some(c), None) if !c.value.replication_ok(max_lag_us) => ("DEGRADED", Color::Yellow),
+        (Some(c), None) if c.value.paused => ("PAUSED", Color::Yellow),
+        (Some(_), None) => ("OK", Color::Green),
+    }
+}
+
+/// Draw the multi-cluster dashboard
+pub fn draw_clusters(frame: &mut Frame, area: Rect, clusters: &[ClusterHandle], selected: usize, current: usize) {
+    let rows: Vec<Row> = clusters
+        .iter()
+        .enumerate()
+        .map(|(i, handle)| {
+            let snapshot = handle.collector.snapshot();
+            let cluster = snapshot.cluster.as_ref().map(|s| &s.value);
+            let (health, color) = cluster_health(&snapshot, handle.config.max_replication_lag_us());
+            let worst_lag = cluster.map_or("-".to_string(), |c| {
+                let replicas = c.members.iter().filter(|n| n.role != "leader");
+                // Unknown lag is worse than any known one
+                match replicas.map(|n| n.lag.map_or(u64::MAX, |l| l)).max() {
+                    Some(u64::MAX) => "unknown".to_string(),
+                    lag => optional_label(lag),
+                }
+            });
+
+            let marker = if i == current { "* " } else { "  " };
+            let row = Row::new(vec![
+                Cell::from(format!("{marker}{}", handle.name)),
+                Cell::from(cluster.map_or("-", |c| c.scope.as_str()).to_string()),
+                Cell::from(cluster.and_then(|c| c.leader_node_name.as_deref()).unwrap_or("-").to_string()),
+                Cell::from(cluster.map_or("-".to_string(), |c| c.members.len().to_string())),
+                Cell::from(worst_lag),
+                Cell::from(match cluster {
+                    Some(c) if c.paused => "yes",
+                    Some(_) => "no",
+                    None => "-",
+                }),
+                Cell::from(health).style(Style::default().fg(color)),
+                Cell::from(age_label(snapshot.cluster.as_ref())),
+            ]);
+
+            if i == selected {
+                row.style(Style::default().bg(Color::DarkGray))
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(20),
+            Constraint::Length(16),
+            Constraint::Length(14),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(7),
+            Constraint::Length(12),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(["Cluster", "Scope", "Leader", "Members", "Worst lag", "Paused", "Health", "Updated"])
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .title("Clusters (Up/Down to select, Enter to open, * shown in the other tabs)")
+            .borders(Borders::ALL),
+    );
+
+    frame.render_widget(table, area);
+}
+
+/// Draw the dynamic configuration tab
+#[allow(clippy::too_many_arguments)]
+pub fn draw_config(
+    frame: &mut Frame,
+    area: Rect,
+    snapshot: &Snapshot,
+    selected: usize,
+    collapsed: &HashSet<String>,
+    edit: Option<&ConfigEdit>,
+    pending: Option<&PendingRequest>,
+    result: Option<&Result<String, String>>,
+) {
+    let inner_area = layout::create_config_layout(area);
+
+    // Render the outer block
+    let outer_block = Block::default()
+        .title(format!(
+            "Dynamic Configuration ({})",
+            age_label(snapshot.dynamic_config.as_ref())
+        ))
+        .borders(Borders::ALL);
+    frame.render_widget(outer_block, area);
+
+    let banner_height = if snapshot.dynamic_config_error.is_some() { 4 } else { 0 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(banner_height),
+            Constraint::Min(3),
+            Constraint::Length(7),
+        ])
+        .split(inner_area);
+
+    if let Some(error) = &snapshot.dynamic_config_error {
+        let banner = error_banner("Patroni /config", error, snapshot.dynamic_config.as_ref());
+        frame.render_widget(banner, chunks[0]);
+    }
+
+    // Tree of sections and settings, indented by depth
+    let entries = snapshot
+        .dynamic_config
+        .as_ref()
+        .map_or(vec![], |s| flatten_config(&s.value, collapsed));
+    let items: Vec<ListItem> = entries
+        .iter()
+        .map(|entry| {
+            let indent = "  ".repeat(entry.depth());
+            let line = match &entry.value {
+                None => Line::from(vec![
+                    Span::raw(indent),
+                    Span::raw(if entry.collapsed { "▸ " } else { "▾ " }),
+                    Span::styled(entry.key().to_string(), Style::default().add_modifier(Modifier::BOLD)),
+                ]),
+                Some(value) => Line::from(vec![
+                    Span::raw(format!("{indent}  {}: ", entry.key())),
+                    Span::styled(display_value(value), Style::default().fg(Color::Cyan)),
+                ]),
+            };
+            ListItem::new(line)
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(
+        (!entries.is_empty()).then(|| selected.min(entries.len() - 1)),
+    );
+    let tree = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("PATCH /config settings"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(tree, chunks[1], &mut state);
+
+    let key = |text: &'static str| Span::styled(text, Style::default().add_modifier(Modifier::BOLD));
+    let (title, lines) = match (edit, pending, result) {
+        (Some(edit), _, _) if edit.confirmation => {
+            let path = edit.path.join(".");
+            let new_value = edit.new_value();
+            let added = if new_value.is_null() {
+                format!("  {path} (removed)")
+            } else {
+                format!("+ {path}: {}", display_value(&new_value))
+            };
+            let button = |label: &'static str, active: bool| {
+                if active {
+                    Span::styled(label, Style::default().fg(Color::Black).bg(Color::White))
+                } else {
+                    Span::raw(label)
+                }
+            };
+
+            (
+                "Confirm change",
+                vec![
+                    Line::styled(
+                        format!("- {path}: {}", display_value(&edit.old_value)),
+                        Style::default().fg(Color::Red),
+                    ),
+                    Line::styled(added, Style::default().fg(Color::Green)),
+                    Line::from(format!("PATCH /config {}", config_patch(&edit.path, new_value))),
+                    Line::from(vec![
+                        button("Yes", edit.confirmation_yes),
+                        Span::raw("   "),
+                        button("No", !edit.confirmation_yes),
+                        Span::raw("    Esc: back to editing"),
+                    ]),
+                ],
+            )
+        }
+        (Some(edit), _, _) => (
+            "Edit value",
+            vec![
+                Line::from(format!(
+                    "{} (currently {})",
+                    edit.path.join("."),
+                    display_value(&edit.old_value)
+                )),
+                Line::from(vec![
+                    Span::styled("> ", Style::default().fg(Color::Yellow)),
+                    Span::raw(edit.input.clone()),
+                    Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+                ]),
+                Line::from(""),
+                Line::from(vec![
+                    key("Enter"),
+                    Span::raw(": preview change   "),
+                    key("Esc"),
+                    Span::raw(": cancel   "),
+                    key("null"),
+                    Span::raw(": remove the setting"),
+                ]),
+            ],
+        ),
+        (None, Some(pending), _) => (
+            "Sending",
+            vec![Line::styled(
+                format!("Sending {} to Patroni...", pending.label()),
+                Style::default().fg(Color::Yellow),
+            )],
+        ),
+        (None, None, Some(Ok(message))) => (
+            "Result",
+            vec![Line::styled(message.clone(), Style::default().fg(Color::Green))],
+        ),
+        (None, None, Some(Err(message))) => (
+            "Result",
+            vec![
+                Line::styled(message.clone(), Style::default().fg(Color::Red)),
+                Line::from(""),
+                Line::from("Press Esc to dismiss"),
+            ],
+        ),
+        (None, None, None) => (
+            "Help",
+            vec![
+                Line::from(vec![
+                    key("j/k"),
+                    Span::raw(": move   "),
+                    key("Enter"),
+                    Span::raw(": edit value or toggle section"),
+                ]),
+                Line::from(vec![key("h/l"), Span::raw(": collapse/expand section")]),
+            ],
+        ),
+    };
+
+    let panel = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(panel, chunks[2]);
 }
 
 /// Draw the logs tab
+#[allow(clippy::too_many_arguments)]
 pub fn draw_logs(
     frame: &mut Frame,
     area: Rect,
@@ -243,7 +1253,7 @@ pub fn draw_logs(
     selected: usize,
     scroll: u16,
     focus_right: bool,
-    lines: &[String],
+    logs: Option<&Sample<Vec<String>>>,
     selected_service: &str,
 ) {
     let (outer_area, chunks) = layout::create_logs_layout(area);
@@ -271,7 +1281,11 @@ pub fn draw_logs(
     frame.render_widget(svc_list, chunks[0]);
 
     // Render the log content
-    let text: Vec<Line> = lines.iter().map(|l| Line::from(l.clone())).collect();
+    let text: Vec<Line> = logs
+        .map_or(&[][..], |s| &s.value)
+        .iter()
+        .map(|l| Line::from(l.clone()))
+        .collect();
 
     let border_style = if focus_right {
         Style::default().fg(Color::Yellow)
@@ -282,7 +1296,7 @@ pub fn draw_logs(
     let logs = Paragraph::new(text)
         .block(
             Block::default()
-                .title(format!("{} log", selected_service))
+                .title(format!("{} log ({})", selected_service, age_label(logs)))
                 .borders(Borders::ALL)
                 .border_style(border_style),
         )
@@ -292,18 +1306,216 @@ pub fn draw_logs(
     frame.render_widget(logs, chunks[1]);
 }
 
+/// Line of an action option: its key, label and current value, or the text
+/// input while the option is being edited
+fn option_line(key: &'static str, label: &'static str, value: String, editing: bool) -> Line<'static> {
+    let mut spans = vec![
+        Span::styled(key, Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(format!(": {label}: ")),
+    ];
+    if editing {
+        spans.push(Span::styled("> ", Style::default().fg(Color::Yellow)));
+        spans.push(Span::raw(value));
+        spans.push(Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)));
+    } else {
+        spans.push(Span::styled(value, Style::default().fg(Color::Cyan)));
+    }
+
+    Line::from(spans)
+}
+
+fn checkbox(checked: bool) -> String {
+    if checked { "yes" } else { "no" }.to_string()
+}
+
+/// Text of an input field, or `default` when it is empty and not being edited
+fn field_value(value: &str, default: &str, editing: bool) -> String {
+    if value.trim().is_empty() && !editing { default } else { value }.to_string()
+}
+
+/// Synchronous replication settings and the members taking part in it
+fn sync_settings_lines(cluster: Option<&ClusterInfo>, dynamic_config: Option<&Value>) -> Vec<Line<'static>> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::styled("Current settings:", bold)];
+    match dynamic_config.map(SyncSettings::from_config) {
+        Some(sync) => {
+            lines.push(Line::from(format!("- synchronous_mode: {}", sync.mode.as_str())));
+            lines.push(Line::from(format!("- synchronous_mode_strict: {}", sync.strict)));
+            lines.push(Line::from(format!("- synchronous_node_count: {}", sync.node_count)));
+        }
+        None => lines.push(Line::styled(
+            "unknown (/config not available)",
+            Style::default().fg(Color::DarkGray),
+        )),
+    }
+
+    let Some(cluster) = cluster else {
+        return lines;
+    };
+    let candidates = topology::sync_candidates(cluster);
+
+    lines.push(Line::from(""));
+    lines.push(Line::styled("Replicas:", bold));
+    for node in cluster.members.iter().filter(|n| !cluster.is_leader(&n.name)) {
+        let eligible = candidates.iter().any(|c| c.name == node.name);
+        let label = if topology::is_sync_standby(node) {
+            node.role.clone()
+        } else if eligible {
+            "can be synchronous".to_string()
+        } else if node.tags.nosync {
+            "nosync tag".to_string()
+        } else if node.tags.replicatefrom.is_some() {
+            "cascading".to_string()
+        } else {
+            node.state.clone()
+        };
+        let style = match (topology::is_sync_standby(node), eligible) {
+            (true, _) => Style::default().fg(Color::Green),
+            (false, true) => Style::default(),
+            (false, false) => Style::default().fg(Color::DarkGray),
+        };
+        lines.push(Line::styled(format!("- {}: {}", node.name, label), style));
+    }
+
+    lines
+}
+
+/// Progress of the last rolling restart, or the planned order if none ran yet
+fn rolling_restart_lines(
+    rolling_restart: Option<&RollingRestart>,
+    cluster: Option<&ClusterInfo>,
+) -> Vec<Line<'static>> {
+    let Some(rolling_restart) = rolling_restart else {
+        let Some(cluster) = cluster else {
+            return vec![Line::from("Cluster state is not available yet")];
+        };
+        let mut order: Vec<String> = cluster
+            .members
+            .iter()
+            .filter(|n| !cluster.is_leader(&n.name))
+            .map(|n| n.name.clone())
+            .collect();
+        if let Some(leader) = &cluster.leader_node_name {
+            order.push("switchover".to_string());
+            order.push(leader.clone());
+        }
+
+        return vec![Line::from(format!("Order: {}", order.join(" -> ")))];
+    };
+
+    let progress = rolling_restart.progress();
+    let mut lines: Vec<Line> = progress
+        .steps
+        .iter()
+        .map(|step| step_line(&step.description, step.status))
+        .collect();
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(progress.message));
+    if !progress.finished {
+        lines.push(Line::from(vec![
+            Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(": Abort before the next step"),
+        ]));
+    }
+
+    lines
+}
+
+/// A step of a rolling restart or of an action follow-up with its marker
+fn step_line(description: &str, status: StepStatus) -> Line<'static> {
+    let (marker, color) = match status {
+        StepStatus::Pending => ("[ ]", Color::Reset),
+        StepStatus::Running => ("[>]", Color::Yellow),
+        StepStatus::Done => ("[x]", Color::Green),
+        StepStatus::Failed => ("[!]", Color::Red),
+        StepStatus::Skipped => ("[-]", Color::DarkGray),
+    };
+    Line::styled(format!("{marker} {description}"), Style::default().fg(color))
+}
+
+/// Draw the follow-up of the last action: its steps and, once finished, the verdict
+fn draw_verification(frame: &mut Frame, area: Rect, progress: &VerificationProgress) {
+    let mut lines: Vec<Line> = progress
+        .steps
+        .iter()
+        .map(|step| step_line(&step.description, step.status))
+        .collect();
+
+    lines.push(Line::from(""));
+    lines.push(match (progress.finished, progress.succeeded) {
+        (false, _) => Line::from(progress.message.clone()),
+        (true, true) => Line::styled(
+            progress.message.clone(),
+            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+        ),
+        (true, false) => Line::styled(
+            progress.message.clone(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+    });
+    if progress.finished {
+        lines.push(Line::from(vec![
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(": Dismiss"),
+        ]));
+    }
+
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!("Progress: {}", progress.title))
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    frame.render_widget(paragraph, area);
+}
+
+/// Describe the pending scheduled switchover and restarts
+fn scheduled_operations(cluster: Option<&ClusterInfo>) -> Vec<Line<'static>> {
+    let Some(cluster) = cluster else {
+        return vec![];
+    };
+    let mut lines = vec![];
+
+    if let Some(switchover) = &cluster.scheduled_switchover {
+        lines.push(Line::styled(
+            format!(
+                "- switchover {} -> {} at {}",
+                switchover.from.as_deref().or(cluster.leader_node_name.as_deref()).unwrap_or("leader"),
+                switchover.to.as_deref().unwrap_or("any replica"),
+                switchover.at
+            ),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    for node in &cluster.members {
+        if let Some(restart) = &node.scheduled_restart {
+            lines.push(Line::styled(
+                format!("- restart {} at {}", node.name, restart.schedule),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+    }
+
+    lines
+}
+
 /// Draw the actions tab
 pub fn draw_actions(
     frame: &mut Frame,
     area: Rect,
-    actions_service: &ActionsService,
-    selected: usize,
-    confirmation: bool,
-    confirmation_yes: bool,
-    target_node: &str,
-    error: &Option<String>,
-    cluster_info: &ClusterInfo,
+    app: &App,
+    cluster: Option<&Sample<ClusterInfo>>,
+    dynamic_config: Option<&Sample<Value>>,
+    max_lag_us: u64,
 ) {
+    let selected = app.action_selected;
+    let confirmation = app.action_confirmation;
+    let confirmation_yes = app.action_confirmation_yes;
+    let target_node = app.action_target_node.as_str();
+    let error = &app.action_error;
+    let schedule = app.action_schedule.as_str();
     let (outer_area, chunks) = layout::create_actions_layout(area);
 
     // Render the outer block
@@ -312,6 +1524,13 @@ pub fn draw_actions(
 
     // Get all available actions
     let actions = Action::all();
+    let failover_disabled = cluster.is_none_or(|c| c.value.has_healthy_leader());
+    // Why promoting a candidate may lose transactions it has not received
+    let lag_warning = |node: &NodeStatus| match node.lag {
+        None => Some("lag unknown"),
+        Some(lag) if lag > max_lag_us => Some("lag above --max-replication-lag-secs"),
+        Some(_) => None,
+    };
 
     // Render the actions list
     let items: Vec<ListItem> = actions
@@ -320,6 +1539,8 @@ pub fn draw_actions(
         .map(|(i, action)| {
             let style = if i == selected {
                 Style::default().fg(Color::Black).bg(Color::White)
+            } else if matches!(action, Action::Failover) && failover_disabled {
+                Style::default().fg(Color::DarkGray)
             } else {
                 Style::default()
             };
@@ -329,6 +1550,7 @@ pub fn draw_actions(
 
     let actions_list = List::new(items)
         .block(Block::default().title("Available Actions").borders(Borders::ALL));
+    let members = cluster.map_or(&[][..], |s| &s.value.members);
     frame.render_widget(actions_list, chunks[0]);
 
     // Render the action details
@@ -353,6 +1575,18 @@ pub fn draw_actions(
             Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(": Execute action"),
         ]));
+        if let Some(pending) = &app.action_pending {
+            lines.push(Line::styled(
+                format!("Sending {} to Patroni...", pending.request.label()),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        for warning in app.ui.cluster().actions_service.audit_warnings() {
+            lines.push(Line::styled(
+                format!("Audit log: {warning}"),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
 
         // Add node selection for node-specific actions
         match action {
@@ -361,7 +1595,7 @@ pub fn draw_actions(
                 lines.push(Line::from("Select target node:"));
 
                 // Add node list
-                for node in &cluster_info.members {
+                for node in members {
                     if node.role != "leader" {
                         let selected_marker = if target_node == node.name { " (selected)" } else { "" };
                         lines.push(Line::from(format!("- {}{}", node.name, selected_marker)));
@@ -374,12 +1608,58 @@ pub fn draw_actions(
                     Span::raw(": Select next node"),
                 ]));
             },
+            Action::Failover => {
+                lines.push(Line::from(""));
+                match cluster.map(|c| &c.value) {
+                    Some(info) if info.has_healthy_leader() => {
+                        lines.push(Line::styled(
+                            format!(
+                                "Disabled: leader {} is healthy, use Switchover",
+                                info.leader_node_name.as_deref().unwrap_or_default()
+                            ),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    Some(info) => {
+                        let leader = info
+                            .leader()
+                            .map_or("No leader".to_string(), |n| format!("Leader {} is {}", n.name, n.state));
+                        lines.push(Line::styled(leader, Style::default().fg(Color::Red)));
+                        lines.push(Line::from(""));
+                        lines.push(Line::from("Select candidate (nofailover members excluded):"));
+
+                        for node in info.failover_candidates() {
+                            let selected_marker = if target_node == node.name { " (selected)" } else { "" };
+                            let mut spans = vec![Span::raw(format!(
+                                "- {} lag {}{}",
+                                node.name,
+                                optional_label(node.lag),
+                                selected_marker
+                            ))];
+                            if let Some(warning) = lag_warning(node) {
+                                spans.push(Span::styled(
+                                    format!("  {warning}"),
+                                    Style::default().fg(Color::Yellow),
+                                ));
+                            }
+                            lines.push(Line::from(spans));
+                        }
+
+                        lines.push(Line::from(""));
+                        lines.push(Line::from(vec![
+                            Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                            Span::raw(": Select next candidate"),
+                        ]));
+                    }
+                    None => lines.push(Line::from("Cluster state is not available yet")),
+                }
+            },
             Action::Restart | Action::Reinitialize => {
                 lines.push(Line::from(""));
                 lines.push(Line::from("Select node:"));
 
                 // Add node list
-                for node in &cluster_info.members {
+                for node in members {
                     let selected_marker = if target_node == node.name { " (selected)" } else { "" };
                     lines.push(Line::from(format!("- {}{}", node.name, selected_marker)));
                 }
@@ -390,17 +1670,205 @@ pub fn draw_actions(
                     Span::raw(": Select next node"),
                 ]));
             },
+            Action::Reload => {
+                lines.push(Line::from(""));
+                lines.push(Line::from("Select node:"));
+
+                let all_marker = if app.action_reload_all { " (selected)" } else { "" };
+                lines.push(Line::from(format!("- all members{all_marker}")));
+                for node in members {
+                    let selected = !app.action_reload_all && target_node == node.name;
+                    let selected_marker = if selected { " (selected)" } else { "" };
+                    lines.push(Line::from(format!("- {}{}", node.name, selected_marker)));
+                }
+
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![
+                    Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(": Select next node   "),
+                    Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(": Toggle all members"),
+                ]));
+
+                if !app.action_reload_results.is_empty() {
+                    lines.push(Line::from(""));
+                    lines.push(Line::styled("Last reload:", Style::default().add_modifier(Modifier::BOLD)));
+                    for (name, result) in &app.action_reload_results {
+                        lines.push(match result {
+                            Ok(()) => Line::styled(format!("- {name}: reloaded"), Style::default().fg(Color::Green)),
+                            Err(e) => Line::styled(format!("- {name}: {e}"), Style::default().fg(Color::Red)),
+                        });
+                    }
+                }
+
+                // Parameters that only take effect after a restart
+                lines.push(Line::from(""));
+                lines.push(Line::styled("Pending restart:", Style::default().add_modifier(Modifier::BOLD)));
+                let pending: Vec<_> = members.iter().filter(|n| n.pending_restart).collect();
+                if pending.is_empty() {
+                    lines.push(Line::from("none"));
+                }
+                for node in pending {
+                    let reasons = pending_restart_reasons(&node.pending_restart_reason);
+                    let reasons = if reasons.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", reasons.join(", "))
+                    };
+                    lines.push(Line::styled(
+                        format!("- {}{}", node.name, reasons),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+            },
+            Action::RollingRestart => {
+                lines.push(Line::from(""));
+                lines.extend(rolling_restart_lines(
+                    app.ui.cluster().rolling_restart.as_ref(),
+                    cluster.map(|c| &c.value),
+                ));
+            },
+            Action::CancelScheduledRestart => {
+                lines.push(Line::from(""));
+                lines.push(Line::from("Select node:"));
+
+                for node in members.iter().filter(|n| n.scheduled_restart.is_some()) {
+                    let selected_marker = if target_node == node.name { " (selected)" } else { "" };
+                    lines.push(Line::from(format!("- {}{}", node.name, selected_marker)));
+                }
+
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![
+                    Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(": Select next node"),
+                ]));
+            },
+            Action::EnableSyncMode | Action::DisableSyncMode | Action::SetSyncNodeCount => {
+                lines.push(Line::from(""));
+                lines.extend(sync_settings_lines(
+                    cluster.map(|c| &c.value),
+                    dynamic_config.map(|c| &c.value),
+                ));
+            },
             _ => {}
         }
 
+        // Conditions and options sent along with the request
+        match action {
+            Action::Restart => {
+                let editing_version = app.action_editing == Some(ActionField::PostgresVersion);
+                let editing_timeout = app.action_editing == Some(ActionField::RestartTimeout);
+
+                lines.push(Line::from(""));
+                lines.push(option_line(
+                    "p",
+                    "Only if a restart is pending",
+                    checkbox(app.action_restart_pending),
+                    false,
+                ));
+                lines.push(option_line(
+                    "r",
+                    "Only if role is",
+                    app.action_restart_role.unwrap_or("any").to_string(),
+                    false,
+                ));
+                lines.push(option_line(
+                    "v",
+                    "Only if version is older than",
+                    field_value(&app.action_postgres_version, "any", editing_version),
+                    editing_version,
+                ));
+                lines.push(option_line(
+                    "o",
+                    "Timeout in seconds",
+                    field_value(&app.action_restart_timeout, "Patroni default", editing_timeout),
+                    editing_timeout,
+                ));
+            }
+            Action::Reinitialize => {
+                lines.push(Line::from(""));
+                lines.push(option_line(
+                    "f",
+                    "Force, even if PostgreSQL is running",
+                    checkbox(app.action_reinit_force),
+                    false,
+                ));
+            }
+            Action::EnableSyncMode => {
+                lines.push(Line::from(""));
+                lines.push(option_line(
+                    "s",
+                    "Strict, commits wait without a standby",
+                    checkbox(app.action_sync_strict),
+                    false,
+                ));
+            }
+            Action::SetSyncNodeCount => {
+                let editing = app.action_editing == Some(ActionField::SyncNodeCount);
+
+                lines.push(Line::from(""));
+                lines.push(option_line(
+                    "c",
+                    "Synchronous node count",
+                    field_value(&app.action_sync_node_count, "not set", editing),
+                    editing,
+                ));
+            }
+            _ => {}
+        }
+
+        // Time to run at, for actions that can be queued
+        if action.can_schedule() {
+            let editing = app.action_editing == Some(ActionField::Schedule);
+
+            lines.push(Line::from(""));
+            lines.push(option_line("t", "Run at", field_value(schedule, "now", editing), editing));
+            if editing {
+                lines.push(Line::from("ISO 8601 with time zone, e.g. 2030-01-31T02:00:00+01:00"));
+            }
+        }
+
+        if app.action_editing.is_some() {
+            lines.push(Line::from(vec![
+                Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": Done   "),
+                Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(": Clear"),
+            ]));
+        }
+
+        // Operations queued in the cluster
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Scheduled operations:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        let scheduled = scheduled_operations(cluster.map(|c| &c.value));
+        if scheduled.is_empty() {
+            lines.push(Line::from("none"));
+        }
+        lines.extend(scheduled);
+
         // Show confirmation dialog if needed
         if confirmation {
             let dialog_text = match action {
                 Action::Switchover => format!("Are you sure you want to switchover to {}?", target_node),
+                Action::Failover => format!("Are you sure you want to fail over to {}?", target_node),
                 Action::Restart => format!("Are you sure you want to restart {}?", target_node),
+                Action::RollingRestart => "Restart all members one at a time?".to_string(),
+                Action::Reload => "Are you sure you want to reload?".to_string(),
                 Action::Reinitialize => format!("Are you sure you want to reinitialize {}?", target_node),
                 Action::PauseCluster => "Are you sure you want to pause the cluster?".to_string(),
                 Action::ResumeCluster => "Are you sure you want to resume the cluster?".to_string(),
+                Action::CancelScheduledSwitchover => "Cancel the scheduled switchover?".to_string(),
+                Action::CancelScheduledRestart => format!("Cancel the scheduled restart of {}?", target_node),
+                Action::EnableSyncMode if app.action_sync_strict => "Enable strict synchronous mode?".to_string(),
+                Action::EnableSyncMode => "Enable synchronous mode?".to_string(),
+                Action::DisableSyncMode => "Disable synchronous mode?".to_string(),
+                Action::SetSyncNodeCount => format!(
+                    "Set synchronous_node_count to {}?",
+                    app.action_sync_node_count.trim()
+                ),
             };
 
             let yes_style = if confirmation_yes {
@@ -415,22 +1883,66 @@ pub fn draw_actions(
                 Style::default().fg(Color::White)
             };
 
+            let mut dialog_lines = vec![Line::from(dialog_text)];
+
+            if action.can_schedule() && !schedule.trim().is_empty() {
+                dialog_lines.push(Line::from(format!("at {}", schedule.trim())));
+            }
+            match action {
+                Action::Restart => {
+                    let summary = app.restart_options().map(|o| o.summary()).unwrap_or_default();
+                    dialog_lines.extend(summary.into_iter().map(Line::from));
+                }
+                Action::Reinitialize if app.action_reinit_force => {
+                    dialog_lines.push(Line::from("forced, even if PostgreSQL is running"));
+                }
+                _ => {}
+            }
+
+            if matches!(action, Action::Failover)
+                && let Some(warning) = members
+                    .iter()
+                    .find(|n| n.name == target_node)
+                    .and_then(lag_warning)
+            {
+                dialog_lines.push(Line::styled(
+                    format!("Warning: {warning}"),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ));
+            }
+
+            // Patroni caps the synchronous standbys at the replicas able to be one
+            if matches!(action, Action::SetSyncNodeCount)
+                && let (Ok(count), Some(info)) = (app.sync_node_count(), cluster.map(|c| &c.value))
+                && let candidates = topology::sync_candidates(info).len()
+                && count > candidates as u64
+            {
+                dialog_lines.push(Line::styled(
+                    format!("Warning: only {candidates} replica(s) can be synchronous"),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ));
+            }
+
+            if let Some(pending) = &app.action_preflight {
+                dialog_lines.push(Line::from(""));
+                dialog_lines.extend(preflight_lines(pending.result().as_deref(), app.action_override));
+            }
+
+            dialog_lines.push(Line::from(""));
+            dialog_lines.push(Line::from(vec![
+                Span::styled("Yes", yes_style),
+                Span::raw("   "),
+                Span::styled("No", no_style),
+            ]));
+
             // Create confirmation dialog
-            let dialog = Paragraph::new(vec![
-                Line::from(dialog_text),
-                Line::from(""),
-                Line::from(vec![
-                    Span::styled("Yes", yes_style),
-                    Span::raw("   "),
-                    Span::styled("No", no_style),
-                ]),
-            ])
-            .block(Block::default().title("Confirm Action").borders(Borders::ALL))
-            .alignment(ratatui::layout::Alignment::Center);
+            let dialog_height = dialog_lines.len() as u16 + 2;
+            let dialog = Paragraph::new(dialog_lines)
+                .block(Block::default().title("Confirm Action").borders(Borders::ALL))
+                .alignment(ratatui::layout::Alignment::Center);
 
-            // Calculate dialog position
-            let dialog_width = 50;
-            let dialog_height = 5;
+            // Calculate dialog position; the pre-flight checks need more room
+            let dialog_width = if app.action_preflight.is_some() { 76 } else { 50 }.min(area.width);
             let dialog_x = (area.width.saturating_sub(dialog_width)) / 2;
             let dialog_y = (area.height.saturating_sub(dialog_height)) / 2;
 
@@ -470,12 +1982,30 @@ pub fn draw_actions(
 
             frame.render_widget(dialog, dialog_area);
         } else {
+            // The follow-up of the last action goes below the details
+            let progress = app.ui.cluster().verification.as_ref().map(|v| v.progress());
+            let details_area = match &progress {
+                Some(progress) => {
+                    let [details_area, progress_area] = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(3), Constraint::Length(progress.steps.len() as u16 + 5)])
+                        .areas(chunks[1]);
+                    draw_verification(frame, progress_area, progress);
+                    details_area
+                }
+                None => chunks[1],
+            };
+
             // Render the details in the right panel
             let details = Paragraph::new(lines)
-                .block(Block::default().title("Details").borders(Borders::ALL))
+                .block(
+                    Block::default()
+                        .title(format!("Details (cluster {})", age_label(cluster)))
+                        .borders(Borders::ALL),
+                )
                 .wrap(Wrap { trim: true });
 
-            frame.render_widget(details, chunks[1]);
+            frame.render_widget(details, details_area);
         }
     }
 }